@@ -1,15 +1,102 @@
 use tui::style::{Color, Style};
 
+use std::collections::{HashMap, HashSet};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use netstat2::TcpState;
+
+use crate::collector::{CollectorEvent, Snapshot};
 use crate::errors::ConnectionToolsError;
-use netstat2::{
-    get_sockets_info, AddressFamilyFlags, ProtocolFlags, ProtocolSocketInfo, SocketInfo,
-    TcpSocketInfo, UdpSocketInfo,
+use crate::export::{self, Format, RawRecord, SocketRecord, UnixRecord};
+use crate::filter::Filter;
+use crate::format::{format_bytes, format_endpoint, format_throughput, join_pids};
+use crate::history::{History, HISTORY_BYTES, HISTORY_LEN};
+use crate::lifecycle::{Change, LifecycleEvent};
+use crate::metrics::Metrics;
+use crate::processes::{read_start_ticks, ProcessInfo, ProcessKey};
+use crate::recording::Player;
+use crate::signal::{self, Signal};
+use crate::sockets::{
+    Protocol, RawKind, RawSocketRow, SocketKey, SocketRow, SocketsContainer, Throughput,
+    UnixSocketRow,
 };
-use sysinfo::{ProcessExt, SystemExt};
+use crate::sorting::Sorting;
+use crate::source::SourceKind;
+use crate::util::event::Config;
+use crate::util::{RingBuffer, TabsState};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Tab {
+    Sockets,
+    /// Listening TCP sockets and bound UDP sockets.
+    Listening,
+    /// Processes owning sockets, with the number of sockets of each kind.
+    Processes,
+    Stats,
+    /// Sockets opened, closed or changing state, newest first.
+    Events,
+}
+
+impl Tab {
+    pub const ALL: [Tab; 5] = [
+        Tab::Sockets,
+        Tab::Listening,
+        Tab::Processes,
+        Tab::Stats,
+        Tab::Events,
+    ];
 
-pub struct SocketsContainer {
-    pub tcp_sockets: Vec<(TcpSocketInfo, Vec<u32>)>,
-    pub udp_sockets: Vec<(UdpSocketInfo, Vec<u32>)>,
+    fn title(self) -> &'static str {
+        match self {
+            Tab::Sockets => "Sockets",
+            Tab::Listening => "Listening",
+            Tab::Processes => "Processes",
+            Tab::Stats => "Stats",
+            Tab::Events => "Events",
+        }
+    }
+}
+
+/// Lifecycle events kept for the Events tab.
+const EVENT_LOG_LEN: usize = 5000;
+
+/// How long a new socket stays highlighted and a closed one stays listed.
+const HIGHLIGHT_DURATION: Duration = Duration::from_secs(3);
+
+/// How far `[` and `]` jump back and forth in time.
+const SEEK_DURATION: Duration = Duration::from_secs(10);
+
+/// Refresh intervals `+` and `-` go through, in milliseconds.
+const REFRESH_INTERVALS: [u64; 8] = [100, 250, 500, 1000, 2000, 5000, 10_000, 30_000];
+
+/// Why a row of the TCP and UDP tables stands out.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Highlight {
+    None,
+    /// Opened in the last `HIGHLIGHT_DURATION`.
+    New,
+    /// Closed in the last `HIGHLIGHT_DURATION` and kept as a ghost row.
+    Closed,
+}
+
+/// Sockets owned by one process, as shown by the Processes tab.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProcessSummary {
+    pub pid: u32,
+    pub name: Option<String>,
+    pub tcp: usize,
+    pub udp: usize,
+    pub unix: usize,
+    pub raw: usize,
+    /// Ports of listening TCP and bound UDP sockets.
+    pub listening: Vec<u16>,
+    pub throughput: Option<Throughput>,
+}
+
+impl ProcessSummary {
+    pub fn total(&self) -> usize {
+        self.tcp + self.udp + self.unix + self.raw
+    }
 }
 
 #[derive(Debug)]
@@ -17,6 +104,9 @@ pub enum SelectedType {
     Nothing,
     Tcp,
     Udp,
+    Unix,
+    /// Raw IP, packet and netlink sockets.
+    Raw,
 }
 
 impl SelectedType {
@@ -25,6 +115,8 @@ impl SelectedType {
             SelectedType::Nothing => SelectedType::Nothing,
             SelectedType::Tcp => SelectedType::Nothing,
             SelectedType::Udp => SelectedType::Tcp,
+            SelectedType::Unix => SelectedType::Udp,
+            SelectedType::Raw => SelectedType::Unix,
         }
     }
 
@@ -32,65 +124,403 @@ impl SelectedType {
         match &self {
             SelectedType::Nothing => SelectedType::Tcp,
             SelectedType::Tcp => SelectedType::Udp,
-            SelectedType::Udp => SelectedType::Udp,
+            SelectedType::Udp => SelectedType::Unix,
+            SelectedType::Unix => SelectedType::Raw,
+            SelectedType::Raw => SelectedType::Raw,
         }
     }
 }
 
-impl SocketsContainer {
-    pub fn new() -> Self {
-        SocketsContainer {
-            tcp_sockets: Vec::new(),
-            udp_sockets: Vec::new(),
+/// Rows a selection can follow across refreshes.
+trait Keyed {
+    type Key: PartialEq;
+
+    fn key(&self) -> Self::Key;
+}
+
+impl Keyed for SocketRow {
+    type Key = SocketKey;
+
+    fn key(&self) -> SocketKey {
+        SocketRow::key(self)
+    }
+}
+
+impl Keyed for UnixSocketRow {
+    type Key = u32;
+
+    fn key(&self) -> u32 {
+        self.inode
+    }
+}
+
+impl Keyed for RawSocketRow {
+    type Key = (RawKind, u32);
+
+    fn key(&self) -> (RawKind, u32) {
+        (self.kind, self.inode)
+    }
+}
+
+/// Selected row of a pane. It is remembered by socket identity so that it
+/// follows the socket when a refresh reorders the rows.
+#[derive(Debug)]
+struct Selection<K> {
+    key: Option<K>,
+    index: Option<usize>,
+}
+
+impl<K> Default for Selection<K> {
+    fn default() -> Self {
+        Selection {
+            key: None,
+            index: None,
+        }
+    }
+}
+
+impl<K: PartialEq> Selection<K> {
+    fn index(&self) -> Option<usize> {
+        self.index
+    }
+
+    /// `view` holds the indices of the visible `rows`.
+    fn select<R: Keyed<Key = K>>(&mut self, index: Option<usize>, rows: &[R], view: &[usize]) {
+        self.index = index;
+        self.key = index
+            .and_then(|index| view.get(index))
+            .map(|&row_index| rows[row_index].key());
+    }
+
+    /// Moves the selection to wherever its socket ended up; if the socket is
+    /// gone, the row that took its place is selected instead.
+    fn follow<R: Keyed<Key = K>>(&mut self, rows: &[R], view: &[usize]) {
+        let position = self.key.as_ref().and_then(|key| {
+            view.iter()
+                .position(|&row_index| rows[row_index].key() == *key)
+        });
+        match position {
+            Some(position) => self.index = Some(position),
+            None => {
+                let index = clamp_selection(self.index, view.len());
+                self.select(index, rows, view);
+            }
         }
     }
 }
 
+/// A signal about to be sent to the owners of the selected socket, waiting
+/// for confirmation.
+pub struct KillDialog {
+    /// The socket as shown in the dialog.
+    pub socket: String,
+    /// Owners as they were when the dialog opened, with their names.
+    pub owners: Vec<(ProcessKey, Option<String>)>,
+    pub signal: Signal,
+}
+
+impl KillDialog {
+    /// Returns the signal and the pids it was sent to.
+    fn send(&self) -> Result<String, ConnectionToolsError> {
+        let keys: Vec<ProcessKey> = self.owners.iter().map(|(key, _)| *key).collect();
+        signal::send(self.signal, &keys)?;
+        let pids: Vec<u32> = keys.iter().map(|key| key.pid).collect();
+        Ok(format!("{} to {}", self.signal, join_pids(&pids)))
+    }
+}
+
 pub struct App {
     sockets_info_res: Result<SocketsContainer, ConnectionToolsError>,
-    pub tcp_sockets: Vec<String>,
-    pub udp_sockets: Vec<String>,
     pub tcp_sockets_count: usize,
     pub udp_sockets_count: usize,
+    pub unix_sockets_count: usize,
+    pub raw_sockets_count: usize,
     pub selected_type: SelectedType,
-    tcp_selection: Option<usize>,
-    udp_selection: Option<usize>,
+    tcp_selection: Selection<SocketKey>,
+    udp_selection: Selection<SocketKey>,
+    unix_selection: Selection<u32>,
+    raw_selection: Selection<(RawKind, u32)>,
+    pub sorting: Sorting,
+    filter: Filter,
+    pub filter_query: String,
+    pub filter_error: Option<String>,
+    pub filter_editing: bool,
+    /// Shows queue and `tcp_info` columns in the socket tables.
+    pub show_internals: bool,
+    tcp_view: Vec<usize>,
+    udp_view: Vec<usize>,
+    unix_view: Vec<usize>,
+    raw_view: Vec<usize>,
+    /// Position of every Unix socket in the container, to find peers.
+    unix_by_inode: HashMap<u32, usize>,
+    processes: HashMap<u32, ProcessInfo>,
+    process_throughput: HashMap<u32, Throughput>,
+    /// Sum over all sockets, `None` when the source has no byte counters.
+    pub total_throughput: Option<Throughput>,
+    pub refreshing: bool,
+    /// Time between two snapshots.
+    pub refresh_interval: Duration,
+    pub last_refresh_duration: Option<Duration>,
+    pub last_refresh_at: Option<SystemTime>,
+    /// `None` unless the collector was asked to watch destroyed sockets.
+    pub watching_destroyed: Option<Result<(), ConnectionToolsError>>,
+    pub export_format: Format,
+    /// Outcome of the last export, the path written on success.
+    pub last_export: Option<Result<String, ConnectionToolsError>>,
+    /// Path of the recording the snapshots are written to, or why they no
+    /// longer are.
+    pub recording: Option<Result<String, ConnectionToolsError>>,
+    /// Set when playing a recording back instead of collecting snapshots.
+    pub replay: Option<Player>,
+    pub kill_dialog: Option<KillDialog>,
+    /// Outcome of the last signal sent, the signal and pids on success.
+    pub last_signal: Option<Result<String, ConnectionToolsError>>,
     pub info_style: Style,
+    /// New rows.
     pub warning_style: Style,
+    /// Errors in the status line.
     pub error_style: Style,
+    /// Ghost rows of closed sockets.
     pub critical_style: Style,
     pub should_quit: bool,
+    pub source: SourceKind,
+    pub tabs: TabsState<'static>,
+    /// First row shown by the list tabs, which have no selection.
+    pub scroll: usize,
+    /// Counts of every refresh, whatever the filter.
+    pub metrics: Metrics,
+    event_log: RingBuffer<LifecycleEvent>,
+    /// Past live snapshots, oldest first.
+    history: History,
+    /// Index in `history` of the snapshot on screen while live updates are
+    /// paused.
+    viewing: Option<usize>,
 }
 
 impl App {
-    pub fn new() -> App {
+    pub fn new(source: SourceKind) -> App {
         App {
             sockets_info_res: Result::Ok(SocketsContainer::new()),
-            tcp_sockets: Vec::new(),
-            udp_sockets: Vec::new(),
             tcp_sockets_count: 0,
             udp_sockets_count: 0,
+            unix_sockets_count: 0,
+            raw_sockets_count: 0,
             selected_type: SelectedType::Nothing,
-            tcp_selection: None,
-            udp_selection: None,
+            tcp_selection: Selection::default(),
+            udp_selection: Selection::default(),
+            unix_selection: Selection::default(),
+            raw_selection: Selection::default(),
+            sorting: Sorting::new(),
+            filter: Filter::default(),
+            filter_query: String::new(),
+            filter_error: None,
+            filter_editing: false,
+            show_internals: false,
+            tcp_view: Vec::new(),
+            udp_view: Vec::new(),
+            unix_view: Vec::new(),
+            raw_view: Vec::new(),
+            unix_by_inode: HashMap::new(),
+            processes: HashMap::new(),
+            process_throughput: HashMap::new(),
+            total_throughput: None,
+            refreshing: false,
+            refresh_interval: Config::default().tick_rate,
+            last_refresh_duration: None,
+            last_refresh_at: None,
+            watching_destroyed: None,
+            export_format: Format::Json,
+            last_export: None,
+            recording: None,
+            replay: None,
+            kill_dialog: None,
+            last_signal: None,
             info_style: Style::default().fg(Color::White),
             warning_style: Style::default().fg(Color::Yellow),
             error_style: Style::default().fg(Color::Magenta),
             critical_style: Style::default().fg(Color::Red),
             should_quit: false,
+            source,
+            tabs: TabsState::new(Tab::ALL.iter().map(|tab| tab.title()).collect()),
+            scroll: 0,
+            metrics: Metrics::new(),
+            event_log: RingBuffer::new(EVENT_LOG_LEN),
+            history: History::new(HISTORY_LEN, HISTORY_BYTES),
+            viewing: None,
         }
     }
 
-    pub fn update_sockets(&mut self) {
-        let af_flags = AddressFamilyFlags::IPV4 | AddressFamilyFlags::IPV6;
-        let proto_flags = ProtocolFlags::TCP | ProtocolFlags::UDP;
-        let sockets_info = get_sockets_info(af_flags, proto_flags).map_err(|err| {
-            ConnectionToolsError::FailToGetSocketsInfo {
-                message: format!("{}", err),
+    pub fn on_collector_event(&mut self, event: CollectorEvent) {
+        match event {
+            CollectorEvent::WatchingDestroyed(watching) => {
+                self.watching_destroyed = Some(watching);
             }
-        });
-        let tcp_and_upd_sockets = sockets_info.map(split_sockets);
-        self.sockets_info_res = tcp_and_upd_sockets;
+            CollectorEvent::Refreshing => {
+                self.refreshing = true;
+            }
+            CollectorEvent::Snapshot(snapshot) => {
+                self.refreshing = false;
+                self.record_history(&snapshot);
+                let dropped = self.history.push(&snapshot);
+                match self.viewing {
+                    None => self.show_snapshot(*snapshot),
+                    Some(index) if index >= dropped => self.viewing = Some(index - dropped),
+                    // the snapshot on screen is gone: show the oldest one left
+                    Some(_) => {
+                        self.viewing = Some(0);
+                        let oldest = self.history.get(0).expect("in history").clone();
+                        self.show_snapshot(oldest);
+                    }
+                }
+            }
+            CollectorEvent::RecordingFailed(err) => {
+                self.recording = Some(Err(err));
+            }
+        }
+    }
+
+    /// Shows the first snapshot of the recording and plays the rest on
+    /// `on_tick`.
+    pub fn replaying(player: Player) -> App {
+        let mut app = App::new(player.source);
+        app.apply_snapshot(player.snapshot(0).clone());
+        app.replay = Some(player);
+        app
+    }
+
+    pub fn on_tick(&mut self) {
+        let moved = self
+            .replay
+            .as_mut()
+            .and_then(|player| player.tick(Instant::now()));
+        self.replay_from(moved);
+    }
+
+    fn on_replay_key(&mut self, c: char) -> bool {
+        let now = Instant::now();
+        let player = match &mut self.replay {
+            Some(player) => player,
+            None => return false,
+        };
+        let moved = match c {
+            ' ' => {
+                player.toggle_pause(now);
+                None
+            }
+            '.' => player.step(true, now),
+            ',' => player.step(false, now),
+            ']' => player.seek(SEEK_DURATION, true, now),
+            '[' => player.seek(SEEK_DURATION, false, now),
+            _ => return false,
+        };
+        self.replay_from(moved);
+        true
+    }
+
+    /// Shows the snapshot the player moved to from `previous`. The snapshots
+    /// in between only feed the event log and the metrics, which start over
+    /// when going back.
+    fn replay_from(&mut self, previous: Option<usize>) {
+        let (previous, player) = match (previous, self.replay.take()) {
+            (Some(previous), Some(player)) => (previous, player),
+            (_, player) => {
+                self.replay = player;
+                return;
+            }
+        };
+        let position = player.position();
+        let first = if position > previous {
+            previous + 1
+        } else {
+            self.metrics = Metrics::new();
+            self.event_log = RingBuffer::new(EVENT_LOG_LEN);
+            0
+        };
+        for index in first..position {
+            self.record_history(player.snapshot(index));
+        }
+        self.apply_snapshot(player.snapshot(position).clone());
+        self.replay = Some(player);
+    }
+
+    /// Pauses live updates and moves through the past snapshots; `Space`
+    /// goes back to live. Only the screen is paused: snapshots are still
+    /// collected, at the same interval, for the history and the Stats tab.
+    fn on_history_key(&mut self, c: char) -> bool {
+        let last = match self.history.len() {
+            0 => return false,
+            len => len - 1,
+        };
+        let current = self.viewing.unwrap_or(last);
+        let seek = |forward: bool| {
+            let taken_at = self.history.get(current).expect("in history").taken_at;
+            let target = if forward {
+                taken_at + SEEK_DURATION
+            } else {
+                taken_at.checked_sub(SEEK_DURATION).unwrap_or(taken_at)
+            };
+            self.history
+                .iter()
+                .rposition(|snapshot| snapshot.taken_at <= target)
+                .unwrap_or(0)
+        };
+        let viewing = match c {
+            ' ' if self.viewing.is_some() => None,
+            ' ' => Some(last),
+            '.' => Some((current + 1).min(last)),
+            ',' => Some(current.saturating_sub(1)),
+            ']' => Some(seek(true)),
+            '[' => Some(seek(false)),
+            _ => return false,
+        };
+        self.viewing = viewing;
+        let shown = self
+            .history
+            .get(viewing.unwrap_or(last))
+            .expect("in history");
+        self.show_snapshot(shown.clone());
+        true
+    }
+
+    /// How far behind the last snapshot the one on screen is, while live
+    /// updates are paused.
+    pub fn behind_live(&self) -> Option<Duration> {
+        let viewing = self.history.get(self.viewing?)?;
+        let last = self.history.last()?;
+        Some(
+            last.taken_at
+                .duration_since(viewing.taken_at)
+                .unwrap_or_default(),
+        )
+    }
+
+    /// Metrics and event log, which go on while the past is on screen.
+    fn record_history(&mut self, snapshot: &Snapshot) {
+        if let Ok(current) = &snapshot.sockets {
+            self.metrics.record(current, snapshot.taken_at);
+        }
+        for event in &snapshot.events {
+            self.event_log.push(event.clone());
+        }
+    }
+
+    fn apply_snapshot(&mut self, snapshot: Snapshot) {
+        self.record_history(&snapshot);
+        self.show_snapshot(snapshot);
+    }
+
+    fn show_snapshot(&mut self, snapshot: Snapshot) {
+        let mut sockets_container = snapshot.sockets;
+        if let Ok(current) = &mut sockets_container {
+            self.sorting.apply(&mut current.tcp_sockets);
+            self.sorting.apply(&mut current.udp_sockets);
+            self.sorting.apply_unix(&mut current.unix_sockets);
+            self.sorting.apply_raw(&mut current.raw_sockets);
+        }
+        self.sockets_info_res = sockets_container;
+        self.processes = snapshot.processes;
+        self.last_refresh_duration = Some(snapshot.duration);
+        self.last_refresh_at = Some(snapshot.taken_at);
 
         self.tcp_sockets_count = self
             .sockets_info_res
@@ -102,197 +532,968 @@ impl App {
             .as_ref()
             .map(|sockets_container| sockets_container.udp_sockets.len())
             .unwrap_or(0);
-
-        self.tcp_sockets = self
+        self.unix_sockets_count = self
             .sockets_info_res
             .as_ref()
-            .map(|sockets_container| {
-                sockets_container
-                    .tcp_sockets
-                    .iter()
-                    .map(|(tcp_si, pids)| tcp_socket_to_string(tcp_si, pids))
-                    .collect::<Vec<String>>()
-            })
-            .unwrap_or_default();
-        self.udp_sockets = self
+            .map(|sockets_container| sockets_container.unix_sockets.len())
+            .unwrap_or(0);
+        self.raw_sockets_count = self
             .sockets_info_res
             .as_ref()
-            .map(|sockets_container| {
-                sockets_container
-                    .udp_sockets
-                    .iter()
-                    .map(|(udp_si, pids)| udp_socket_to_string(udp_si, pids))
-                    .collect::<Vec<String>>()
-            })
-            .unwrap_or_default();
+            .map(|sockets_container| sockets_container.raw_sockets.len())
+            .unwrap_or(0);
+        if let Ok(current) = &self.sockets_info_res {
+            self.process_throughput = throughput_by_process(current);
+            self.total_throughput = total_throughput(current);
+        }
+        self.refresh_view();
     }
 
-    pub fn on_up(&mut self) {
+    /// Recomputes which sockets pass the filter and moves the selections to
+    /// the new positions of the selected sockets.
+    fn refresh_view(&mut self) {
+        let filter = &self.filter;
+        let matching = |rows: &[SocketRow]| -> Vec<usize> {
+            rows.iter()
+                .enumerate()
+                .filter(|(_, row)| filter.matches(row))
+                .map(|(index, _)| index)
+                .collect()
+        };
+        let (tcp_rows, udp_rows, unix_rows, raw_rows) = container_rows(&self.sockets_info_res);
+        self.tcp_view = matching(tcp_rows);
+        self.udp_view = matching(udp_rows);
+        self.unix_view = unix_rows
+            .iter()
+            .enumerate()
+            .filter(|(_, row)| filter.matches_unix(row))
+            .map(|(index, _)| index)
+            .collect();
+        self.raw_view = raw_rows
+            .iter()
+            .enumerate()
+            .filter(|(_, row)| filter.matches_raw(row))
+            .map(|(index, _)| index)
+            .collect();
+        self.unix_by_inode = unix_rows
+            .iter()
+            .enumerate()
+            .map(|(index, row)| (row.inode, index))
+            .collect();
+
+        self.tcp_selection.follow(tcp_rows, &self.tcp_view);
+        self.udp_selection.follow(udp_rows, &self.udp_view);
+        self.unix_selection.follow(unix_rows, &self.unix_view);
+        self.raw_selection.follow(raw_rows, &self.raw_view);
+    }
+
+    pub fn tcp_sockets(&self) -> Vec<&SocketRow> {
+        match &self.sockets_info_res {
+            Ok(sockets_container) => self
+                .tcp_view
+                .iter()
+                .map(|&index| &sockets_container.tcp_sockets[index])
+                .collect(),
+            Err(_) => Vec::new(),
+        }
+    }
+
+    pub fn udp_sockets(&self) -> Vec<&SocketRow> {
+        match &self.sockets_info_res {
+            Ok(sockets_container) => self
+                .udp_view
+                .iter()
+                .map(|&index| &sockets_container.udp_sockets[index])
+                .collect(),
+            Err(_) => Vec::new(),
+        }
+    }
+
+    pub fn unix_sockets(&self) -> Vec<&UnixSocketRow> {
+        match &self.sockets_info_res {
+            Ok(sockets_container) => self
+                .unix_view
+                .iter()
+                .map(|&index| &sockets_container.unix_sockets[index])
+                .collect(),
+            Err(_) => Vec::new(),
+        }
+    }
+
+    pub fn raw_sockets(&self) -> Vec<&RawSocketRow> {
+        match &self.sockets_info_res {
+            Ok(sockets_container) => self
+                .raw_view
+                .iter()
+                .map(|&index| &sockets_container.raw_sockets[index])
+                .collect(),
+            Err(_) => Vec::new(),
+        }
+    }
+
+    /// Visible TCP or UDP sockets with the recently closed ones merged in
+    /// sort order, and the position of the selected socket among them.
+    pub fn highlighted_sockets(
+        &self,
+        protocol: Protocol,
+    ) -> (Vec<(&SocketRow, Highlight)>, Option<usize>) {
+        let (live, selected) = match protocol {
+            Protocol::Tcp => (self.tcp_sockets(), self.selected_tcp()),
+            Protocol::Udp => (self.udp_sockets(), self.selected_udp()),
+        };
+        let recent = self.recent_events();
+        let opened: HashSet<SocketKey> = recent
+            .iter()
+            .filter(|event| event.change == Change::Opened)
+            .map(|event| event.socket.key())
+            .collect();
+        let mut ghosts: Vec<&SocketRow> = recent
+            .iter()
+            .filter(|event| event.change == Change::Closed)
+            .map(|event| &event.socket)
+            .filter(|row| row.protocol == protocol && self.filter.matches(row))
+            .collect();
+        ghosts.sort_by(|a, b| self.sorting.compare(a, b));
+
+        let mut rows = Vec::with_capacity(live.len() + ghosts.len());
+        let mut selected_row = None;
+        let mut ghosts = ghosts.into_iter().peekable();
+        for (index, row) in live.into_iter().enumerate() {
+            while let Some(ghost) = ghosts.next_if(|ghost| self.sorting.compare(ghost, row).is_lt())
+            {
+                rows.push((ghost, Highlight::Closed));
+            }
+            if Some(index) == selected {
+                selected_row = Some(rows.len());
+            }
+            let highlight = if opened.contains(&row.key()) {
+                Highlight::New
+            } else {
+                Highlight::None
+            };
+            rows.push((row, highlight));
+        }
+        rows.extend(ghosts.map(|ghost| (ghost, Highlight::Closed)));
+        (rows, selected_row)
+    }
+
+    /// The socket at the other end of a Unix socket connection.
+    pub fn unix_peer(&self, row: &UnixSocketRow) -> Option<&UnixSocketRow> {
+        let sockets_container = self.sockets_info_res.as_ref().ok()?;
+        let index = self.unix_by_inode.get(&row.peer_inode?)?;
+        sockets_container.unix_sockets.get(*index)
+    }
+
+    pub fn tcp_visible_count(&self) -> usize {
+        self.tcp_view.len()
+    }
+
+    pub fn udp_visible_count(&self) -> usize {
+        self.udp_view.len()
+    }
+
+    pub fn unix_visible_count(&self) -> usize {
+        self.unix_view.len()
+    }
+
+    pub fn raw_visible_count(&self) -> usize {
+        self.raw_view.len()
+    }
+
+    fn selected_row(&self) -> Option<&SocketRow> {
+        let sockets_container = self.sockets_info_res.as_ref().ok()?;
         match self.selected_type {
-            SelectedType::Nothing => (),
-            SelectedType::Tcp => {
-                self.tcp_selection = up_select_counter(&self.tcp_selection, &self.tcp_sockets_count)
+            SelectedType::Nothing | SelectedType::Unix | SelectedType::Raw => None,
+            SelectedType::Tcp => self
+                .tcp_view
+                .get(self.tcp_selection.index()?)
+                .map(|&index| &sockets_container.tcp_sockets[index]),
+            SelectedType::Udp => self
+                .udp_view
+                .get(self.udp_selection.index()?)
+                .map(|&index| &sockets_container.udp_sockets[index]),
+        }
+    }
+
+    fn selected_unix_row(&self) -> Option<&UnixSocketRow> {
+        let sockets_container = self.sockets_info_res.as_ref().ok()?;
+        self.unix_view
+            .get(self.unix_selection.index()?)
+            .map(|&index| &sockets_container.unix_sockets[index])
+    }
+
+    fn selected_raw_row(&self) -> Option<&RawSocketRow> {
+        let sockets_container = self.sockets_info_res.as_ref().ok()?;
+        self.raw_view
+            .get(self.raw_selection.index()?)
+            .map(|&index| &sockets_container.raw_sockets[index])
+    }
+
+    /// Moves the Unix selection to the other end of the selected connection.
+    fn select_peer(&mut self) {
+        let peer_index = match self.selected_unix_row().and_then(|row| row.peer_inode) {
+            Some(peer_inode) => self.unix_by_inode.get(&peer_inode).copied(),
+            None => None,
+        };
+        let position = peer_index.and_then(|peer_index| {
+            self.unix_view
+                .iter()
+                .position(|&row_index| row_index == peer_index)
+        });
+        if let Some(position) = position {
+            let (_, _, unix_rows, _) = container_rows(&self.sockets_info_res);
+            self.unix_selection
+                .select(Some(position), unix_rows, &self.unix_view);
+        }
+    }
+
+    pub fn tab(&self) -> Tab {
+        Tab::ALL[self.tabs.index]
+    }
+
+    pub fn on_tab(&mut self) {
+        self.tabs.next();
+        self.scroll = 0;
+    }
+
+    pub fn on_back_tab(&mut self) {
+        if self.kill_dialog.is_some() {
+            return;
+        }
+        self.tabs.previous();
+        self.scroll = 0;
+    }
+
+    /// Listening TCP sockets followed by the bound UDP ones.
+    pub fn listening_sockets(&self) -> Vec<&SocketRow> {
+        let tcp = self
+            .tcp_sockets()
+            .into_iter()
+            .filter(|row| row.state == Some(TcpState::Listen));
+        tcp.chain(self.udp_sockets()).collect()
+    }
+
+    /// Owners of the visible sockets, the ones with most sockets first.
+    pub fn process_summaries(&self) -> Vec<ProcessSummary> {
+        let new_summary = |pid: u32| ProcessSummary {
+            pid,
+            name: self.processes.get(&pid).map(|info| info.name.clone()),
+            tcp: 0,
+            udp: 0,
+            unix: 0,
+            raw: 0,
+            listening: Vec::new(),
+            throughput: self.process_throughput.get(&pid).copied(),
+        };
+        let mut by_pid: HashMap<u32, ProcessSummary> = HashMap::new();
+        for row in self.tcp_sockets() {
+            for &pid in &row.pids {
+                let summary = by_pid.entry(pid).or_insert_with(|| new_summary(pid));
+                summary.tcp += 1;
+                if row.state == Some(TcpState::Listen) {
+                    summary.listening.push(row.local_port);
+                }
             }
-            SelectedType::Udp => {
-                self.udp_selection = up_select_counter(&self.udp_selection, &self.udp_sockets_count)
+        }
+        for row in self.udp_sockets() {
+            for &pid in &row.pids {
+                let summary = by_pid.entry(pid).or_insert_with(|| new_summary(pid));
+                summary.udp += 1;
+                summary.listening.push(row.local_port);
+            }
+        }
+        for row in self.unix_sockets() {
+            for &pid in &row.pids {
+                by_pid.entry(pid).or_insert_with(|| new_summary(pid)).unix += 1;
+            }
+        }
+        for row in self.raw_sockets() {
+            for &pid in &row.pids {
+                by_pid.entry(pid).or_insert_with(|| new_summary(pid)).raw += 1;
+            }
+        }
+
+        let mut summaries: Vec<ProcessSummary> = by_pid.into_values().collect();
+        for summary in &mut summaries {
+            summary.listening.sort_unstable();
+            summary.listening.dedup();
+        }
+        summaries.sort_by(|a, b| b.total().cmp(&a.total()).then(a.pid.cmp(&b.pid)));
+        summaries
+    }
+
+    /// Events up to the snapshot on screen, newest first.
+    pub fn events(&self) -> Vec<&LifecycleEvent> {
+        self.event_log
+            .iter()
+            .rev()
+            .filter(|event| self.is_shown(event) && self.filter.matches(&event.socket))
+            .collect()
+    }
+
+    fn is_shown(&self, event: &LifecycleEvent) -> bool {
+        self.last_refresh_at
+            .is_none_or(|shown_at| event.at <= shown_at)
+    }
+
+    /// Events of the last `HIGHLIGHT_DURATION` before the snapshot on screen.
+    fn recent_events(&self) -> Vec<&LifecycleEvent> {
+        let shown_at = match self.last_refresh_at {
+            Some(shown_at) => shown_at,
+            None => return Vec::new(),
+        };
+        self.event_log
+            .iter()
+            .filter(|event| {
+                shown_at
+                    .duration_since(event.at)
+                    .is_ok_and(|elapsed| elapsed < HIGHLIGHT_DURATION)
+            })
+            .collect()
+    }
+
+    /// Number of visible TCP sockets in each state, the most common first.
+    pub fn tcp_state_counts(&self) -> Vec<(TcpState, usize)> {
+        let mut counts: Vec<(TcpState, usize)> = Vec::new();
+        for row in self.tcp_sockets() {
+            let state = match row.state {
+                Some(state) => state,
+                None => continue,
+            };
+            match counts.iter_mut().find(|(counted, _)| *counted == state) {
+                Some((_, count)) => *count += 1,
+                None => counts.push((state, 1)),
             }
         }
+        counts.sort_by(|a, b| b.1.cmp(&a.1).then((a.0 as u8).cmp(&(b.0 as u8))));
+        counts
+    }
+
+    fn list_len(&self) -> usize {
+        match self.tab() {
+            Tab::Listening => self.listening_sockets().len(),
+            Tab::Processes => self.process_summaries().len(),
+            Tab::Events => self.events().len(),
+            Tab::Sockets | Tab::Stats => 0,
+        }
+    }
+
+    pub fn on_up(&mut self) {
+        if self.kill_dialog.is_some() {
+            return;
+        }
+        match self.tab() {
+            Tab::Sockets => self.move_selection(up_select_counter),
+            _ => self.scroll = self.scroll.saturating_sub(1),
+        }
     }
 
     pub fn on_down(&mut self) {
+        if self.kill_dialog.is_some() {
+            return;
+        }
+        match self.tab() {
+            Tab::Sockets => self.move_selection(down_select_counter),
+            _ => {
+                if self.scroll + 1 < self.list_len() {
+                    self.scroll += 1;
+                }
+            }
+        }
+    }
+
+    fn move_selection(&mut self, counter: fn(&Option<usize>, &usize) -> Option<usize>) {
+        let (tcp_rows, udp_rows, unix_rows, raw_rows) = container_rows(&self.sockets_info_res);
         match self.selected_type {
             SelectedType::Nothing => (),
             SelectedType::Tcp => {
-                self.tcp_selection =
-                    down_select_counter(&self.tcp_selection, &self.tcp_sockets_count)
+                let index = counter(&self.tcp_selection.index(), &self.tcp_view.len());
+                self.tcp_selection.select(index, tcp_rows, &self.tcp_view);
             }
             SelectedType::Udp => {
-                self.udp_selection =
-                    down_select_counter(&self.udp_selection, &self.udp_sockets_count)
+                let index = counter(&self.udp_selection.index(), &self.udp_view.len());
+                self.udp_selection.select(index, udp_rows, &self.udp_view);
+            }
+            SelectedType::Unix => {
+                let index = counter(&self.unix_selection.index(), &self.unix_view.len());
+                self.unix_selection
+                    .select(index, unix_rows, &self.unix_view);
+            }
+            SelectedType::Raw => {
+                let index = counter(&self.raw_selection.index(), &self.raw_view.len());
+                self.raw_selection.select(index, raw_rows, &self.raw_view);
             }
         }
     }
 
     pub fn selected_tcp(&self) -> Option<usize> {
         match self.selected_type {
-            SelectedType::Udp => None,
-            SelectedType::Nothing => None,
-            SelectedType::Tcp => self.tcp_selection,
+            SelectedType::Tcp => self.tcp_selection.index(),
+            _ => None,
         }
     }
 
     pub fn selected_udp(&self) -> Option<usize> {
         match self.selected_type {
-            SelectedType::Nothing => None,
-            SelectedType::Tcp => None,
-            SelectedType::Udp => self.udp_selection,
+            SelectedType::Udp => self.udp_selection.index(),
+            _ => None,
+        }
+    }
+
+    pub fn selected_unix(&self) -> Option<usize> {
+        match self.selected_type {
+            SelectedType::Unix => self.unix_selection.index(),
+            _ => None,
+        }
+    }
+
+    pub fn selected_raw(&self) -> Option<usize> {
+        match self.selected_type {
+            SelectedType::Raw => self.raw_selection.index(),
+            _ => None,
         }
     }
 
     pub fn on_right(&mut self) {
+        if let Some(dialog) = &mut self.kill_dialog {
+            dialog.signal = dialog.signal.next();
+            return;
+        }
         self.selected_type = self.selected_type.right();
     }
 
     pub fn on_left(&mut self) {
+        if let Some(dialog) = &mut self.kill_dialog {
+            dialog.signal = dialog.signal.previous();
+            return;
+        }
         self.selected_type = self.selected_type.left();
     }
 
     pub fn on_key(&mut self, c: char) {
+        if self.on_kill_dialog_key(c) {
+            return;
+        }
+        if c == '\t' {
+            self.on_tab();
+            return;
+        }
+        if self.filter_editing {
+            if c == '\n' {
+                self.filter_editing = false;
+            } else {
+                self.filter_query.push(c);
+                self.apply_filter_query();
+            }
+            return;
+        }
+        if self.on_replay_key(c) || self.on_history_key(c) {
+            return;
+        }
+
         match c {
             'q' => {
                 self.should_quit = true;
             }
+            's' => {
+                self.sorting.key = self.sorting.key.next();
+                self.resort();
+            }
+            'r' => {
+                self.sorting.reversed = !self.sorting.reversed;
+                self.resort();
+            }
+            '/' => {
+                self.filter_editing = true;
+            }
+            'i' => {
+                self.show_internals = !self.show_internals;
+            }
+            'p' => {
+                self.select_peer();
+            }
+            'e' => {
+                self.last_export = Some(self.export());
+            }
+            'k' => match self.kill_dialog() {
+                Ok(dialog) => {
+                    self.kill_dialog = Some(dialog);
+                    self.last_signal = None;
+                }
+                Err(err) => self.last_signal = Some(Err(err)),
+            },
+            '+' | '=' if self.replay.is_none() => {
+                let faster = REFRESH_INTERVALS
+                    .iter()
+                    .rev()
+                    .map(|millis| Duration::from_millis(*millis))
+                    .find(|interval| *interval < self.refresh_interval);
+                self.refresh_interval = faster.unwrap_or(self.refresh_interval);
+            }
+            '-' if self.replay.is_none() => {
+                let slower = REFRESH_INTERVALS
+                    .iter()
+                    .map(|millis| Duration::from_millis(*millis))
+                    .find(|interval| *interval > self.refresh_interval);
+                self.refresh_interval = slower.unwrap_or(self.refresh_interval);
+            }
             _ => {}
         }
     }
 
-    pub fn on_tick(&mut self) {
-        self.update_sockets();
+    pub fn on_backspace(&mut self) {
+        if self.filter_editing {
+            self.filter_query.pop();
+            self.apply_filter_query();
+        }
+    }
+
+    pub fn on_esc(&mut self) {
+        if self.kill_dialog.take().is_some() {
+            return;
+        }
+        self.filter_editing = false;
+        self.filter_query.clear();
+        self.apply_filter_query();
+    }
+
+    /// `Enter` or `y` sends the signal, `n` cancels and `Tab` picks the next
+    /// signal, like `→`.
+    fn on_kill_dialog_key(&mut self, c: char) -> bool {
+        let dialog = match &mut self.kill_dialog {
+            Some(dialog) => dialog,
+            None => return false,
+        };
+        match c {
+            '\n' | 'y' => self.last_signal = self.kill_dialog.take().map(|dialog| dialog.send()),
+            'n' => self.kill_dialog = None,
+            '\t' => dialog.signal = dialog.signal.next(),
+            _ => {}
+        }
+        true
+    }
+
+    /// Asks which signal to send to the owners of the selected socket.
+    fn kill_dialog(&self) -> Result<KillDialog, ConnectionToolsError> {
+        let signal_error = |message: String| ConnectionToolsError::Signal { message };
+        if self.replay.is_some() {
+            return Err(signal_error(
+                "the processes of a recording may not be running here".to_owned(),
+            ));
+        }
+        let (socket, pids) = self
+            .selected_owners()
+            .ok_or_else(|| signal_error("select a socket first".to_owned()))?;
+        let owners: Vec<(ProcessKey, Option<String>)> = pids
+            .iter()
+            .filter_map(|&pid| match self.processes.get(&pid) {
+                Some(info) => Some((info.key, Some(info.name.clone()))),
+                None => {
+                    let start_ticks = read_start_ticks(pid)?;
+                    Some((ProcessKey { pid, start_ticks }, None))
+                }
+            })
+            .collect();
+        if owners.is_empty() {
+            return Err(signal_error(format!(
+                "no running owner of {} found",
+                socket
+            )));
+        }
+        Ok(KillDialog {
+            socket,
+            owners,
+            signal: Signal::Term,
+        })
+    }
+
+    /// The selected socket as shown in the kill dialog, and its owners.
+    fn selected_owners(&self) -> Option<(String, &[u32])> {
+        match self.selected_type {
+            SelectedType::Nothing => None,
+            SelectedType::Tcp | SelectedType::Udp => self.selected_row().map(|row| {
+                let mut socket = format!(
+                    "{} {}",
+                    row.protocol,
+                    format_endpoint(row.local_addr, row.local_port)
+                );
+                if let (Some(addr), Some(port)) = (row.remote_addr, row.remote_port) {
+                    socket.push_str(&format!(" -> {}", format_endpoint(addr, port)));
+                }
+                (socket, &row.pids[..])
+            }),
+            SelectedType::Unix => self.selected_unix_row().map(|row| {
+                let path = row.path.as_deref().unwrap_or("unnamed");
+                (format!("UNIX {}", path), &row.pids[..])
+            }),
+            SelectedType::Raw => self.selected_raw_row().map(|row| {
+                let socket = format!("{} {}", row.kind, row.protocol_name());
+                (socket, &row.pids[..])
+            }),
+        }
+    }
+
+    /// An unfinished query keeps the last valid filter active and only
+    /// reports why the current text can't be used.
+    fn apply_filter_query(&mut self) {
+        match Filter::parse(&self.filter_query) {
+            Ok(filter) => {
+                self.filter = filter;
+                self.filter_error = None;
+                self.refresh_view();
+            }
+            Err(err) => {
+                self.filter_error = Some(format!("{}", err));
+            }
+        }
+    }
+
+    /// Writes the visible sockets of the focused pane to a new file in the
+    /// working directory, TCP and UDP together, and returns its name.
+    fn export(&self) -> Result<String, ConnectionToolsError> {
+        let taken_at = self.last_refresh_at.unwrap_or_else(SystemTime::now);
+        let seconds = taken_at
+            .duration_since(UNIX_EPOCH)
+            .map(|since_epoch| since_epoch.as_secs())
+            .unwrap_or_default();
+        let format = self.export_format;
+        match self.selected_type {
+            SelectedType::Nothing | SelectedType::Tcp | SelectedType::Udp => {
+                let records: Vec<SocketRecord> = self
+                    .tcp_sockets()
+                    .into_iter()
+                    .chain(self.udp_sockets())
+                    .map(|row| SocketRecord::new(row, &self.processes, taken_at))
+                    .collect();
+                export::write_new_file(&format!("poke-{}", seconds), format, &records)
+            }
+            SelectedType::Unix => {
+                let records: Vec<UnixRecord> = self
+                    .unix_sockets()
+                    .into_iter()
+                    .map(|row| UnixRecord::new(row, taken_at))
+                    .collect();
+                export::write_new_file(&format!("poke-unix-{}", seconds), format, &records)
+            }
+            SelectedType::Raw => {
+                let records: Vec<RawRecord> = self
+                    .raw_sockets()
+                    .into_iter()
+                    .map(|row| RawRecord::new(row, taken_at))
+                    .collect();
+                export::write_new_file(&format!("poke-raw-{}", seconds), format, &records)
+            }
+        }
+    }
+
+    pub fn is_filtered(&self) -> bool {
+        !self.filter.is_empty()
+    }
+
+    fn resort(&mut self) {
+        if let Ok(sockets_container) = &mut self.sockets_info_res {
+            self.sorting.apply(&mut sockets_container.tcp_sockets);
+            self.sorting.apply(&mut sockets_container.udp_sockets);
+            self.sorting.apply_unix(&mut sockets_container.unix_sockets);
+            self.sorting.apply_raw(&mut sockets_container.raw_sockets);
+        }
+        self.refresh_view();
     }
 
     pub fn selected_socket_info(&self) -> String {
         match self.selected_type {
             SelectedType::Nothing => "choose socket with arrow keys".to_owned(),
-            SelectedType::Tcp => match &self.sockets_info_res {
-                Err(_) => "fail to get sockets info".to_owned(),
-                Ok(sockets_info) => {
-                    let selected_socket =
-                        &sockets_info.tcp_sockets[self.tcp_selection.unwrap_or(0)];
-                    let pids = &selected_socket.1;
-
-                    //todo: move systemInfo outside
-                    let mut system = sysinfo::System::new_all();
-
-                    // First we update all information of our system struct.
-                    system.refresh_all();
-
-                    // Now let's print every process' id and name:
-                    let pids_info = pids
-                        .iter()
-                        .map(|&pid| {
-                            system
-                                .get_process(pid as i32)
-                                .map(|proc_| {
-                                    format!(
-                                        "pid {}::\nname {}\nstatus: {:?}\ncmd: {:?}\nexe: {:?}\nenviron: {:?}\nmemory: {}\nvirtual memory: {}\nstart time: {}\ncpu usage: {}",
-                                        pid,
-                                        proc_.name(),
-                                        proc_.status(),
-                                        proc_.cmd(),
-                                        proc_.exe(),
-                                        proc_.environ(),
-                                        proc_.memory(),
-                                        proc_.virtual_memory(),
-                                        proc_.start_time(),
-                                        proc_.cpu_usage(),
-                                    )
-                                })
-                                .unwrap_or("todo: fix me".to_owned())
-                        })
-                        .collect();
-                    pids_info
+            SelectedType::Tcp | SelectedType::Udp => {
+                let view = match self.selected_type {
+                    SelectedType::Tcp => &self.tcp_view,
+                    _ => &self.udp_view,
+                };
+                match (&self.sockets_info_res, self.selected_row()) {
+                    (Err(_), _) => "fail to get sockets info".to_owned(),
+                    (Ok(_), None) => nothing_selected(view),
+                    (Ok(_), Some(selected_socket)) => {
+                        socket_details(selected_socket, &self.processes, &self.process_throughput)
+                    }
+                }
+            }
+            SelectedType::Unix => match (&self.sockets_info_res, self.selected_unix_row()) {
+                (Err(_), _) => "fail to get sockets info".to_owned(),
+                (Ok(_), None) => nothing_selected(&self.unix_view),
+                (Ok(_), Some(selected_socket)) => unix_socket_details(
+                    selected_socket,
+                    self.unix_peer(selected_socket),
+                    &self.processes,
+                ),
+            },
+            SelectedType::Raw => match (&self.sockets_info_res, self.selected_raw_row()) {
+                (Err(_), _) => "fail to get sockets info".to_owned(),
+                (Ok(_), None) => nothing_selected(&self.raw_view),
+                (Ok(_), Some(selected_socket)) => {
+                    raw_socket_details(selected_socket, &self.processes)
                 }
             },
-            SelectedType::Udp => "todo: implement in the same way as for TCP".to_owned(),
         }
     }
 }
 
-fn split_sockets(sockets_info: Vec<SocketInfo>) -> SocketsContainer {
-    let sockets_len = sockets_info.len();
-    let mut sockets_tuple = sockets_info.into_iter().fold(
-        (
-            Vec::with_capacity(sockets_len),
-            Vec::with_capacity(sockets_len),
-        ),
-        |mut res_tuple, si| {
-            match si {
-                SocketInfo {
-                    protocol_socket_info: ProtocolSocketInfo::Tcp(tcp_socket_info),
-                    associated_pids,
-                    ..
-                } => res_tuple.0.push((tcp_socket_info, associated_pids)),
-
-                SocketInfo {
-                    protocol_socket_info: ProtocolSocketInfo::Udp(udp_socket_info),
-                    associated_pids,
-                    ..
-                } => res_tuple.1.push((udp_socket_info, associated_pids)),
-            }
-
-            res_tuple
-        },
+/// Details of a pane with no row selected, given its visible rows.
+fn nothing_selected(view: &[usize]) -> String {
+    if view.is_empty() {
+        "no sockets match the filter".to_owned()
+    } else {
+        "choose socket with arrow keys".to_owned()
+    }
+}
+
+fn raw_socket_details(socket: &RawSocketRow, processes: &HashMap<u32, ProcessInfo>) -> String {
+    let mut details = format!(
+        "{} {} [{}]; inode: {}",
+        socket.kind,
+        socket.protocol_name(),
+        socket.endpoint,
+        socket.inode
     );
+    if let Some(uid) = socket.uid {
+        details.push_str(&format!("; uid: {}", uid));
+    }
+    details.push('\n');
 
-    sockets_tuple.0.shrink_to_fit();
-    sockets_tuple.1.shrink_to_fit();
-    SocketsContainer {
-        tcp_sockets: sockets_tuple.0,
-        udp_sockets: sockets_tuple.1,
+    if socket.pids.is_empty() {
+        details.push_str("no owning process found (it may belong to another user or the kernel)");
+        return details;
     }
+
+    for &pid in &socket.pids {
+        details.push('\n');
+        details.push_str(&process_details(processes, pid, None));
+    }
+    details
 }
 
-fn tcp_socket_to_string(tcp_si: &TcpSocketInfo, associated_pids: &[u32]) -> String {
-    format!(
-        "local[{} : {}] -> remote [{} : {}]; pids{:?}; state: {}",
-        tcp_si.local_addr,
-        tcp_si.local_port,
-        tcp_si.remote_addr,
-        tcp_si.remote_port,
-        associated_pids,
-        tcp_si.state
-    )
+fn unix_socket_details(
+    socket: &UnixSocketRow,
+    peer: Option<&UnixSocketRow>,
+    processes: &HashMap<u32, ProcessInfo>,
+) -> String {
+    let mut details = format!(
+        "UNIX {} [{}]; state: {}; inode: {}\n",
+        socket.socket_type,
+        socket.path.as_deref().unwrap_or("unnamed"),
+        socket.state,
+        socket.inode
+    );
+    match (socket.peer_inode, peer) {
+        (Some(_), Some(peer)) => details.push_str(&format!(
+            "peer: inode {} [{}] pids {:?} process {} (p to jump)\n",
+            peer.inode,
+            peer.path.as_deref().unwrap_or("unnamed"),
+            peer.pids,
+            peer.process_name.as_deref().unwrap_or("unknown")
+        )),
+        (Some(peer_inode), None) => {
+            details.push_str(&format!("peer: inode {} (not visible)\n", peer_inode))
+        }
+        (None, _) => (),
+    }
+
+    if socket.pids.is_empty() {
+        details.push_str("no owning process found (it may belong to another user)");
+        return details;
+    }
+
+    for &pid in &socket.pids {
+        details.push('\n');
+        details.push_str(&process_details(processes, pid, None));
+    }
+    details
 }
 
-fn udp_socket_to_string(udp_si: &UdpSocketInfo, associated_pids: &[u32]) -> String {
-    format!(
-        "local[{} : {}] -> *:* pids{:?}",
-        udp_si.local_addr, udp_si.local_port, associated_pids
+fn socket_details(
+    socket: &SocketRow,
+    processes: &HashMap<u32, ProcessInfo>,
+    process_throughput: &HashMap<u32, Throughput>,
+) -> String {
+    let remote = match (socket.remote_addr, socket.remote_port) {
+        (Some(remote_addr), Some(remote_port)) => format!("{} : {}", remote_addr, remote_port),
+        _ => "*:*".to_owned(),
+    };
+    let mut details = format!(
+        "{} {} local [{} : {}] -> remote [{}]",
+        socket.protocol, socket.family, socket.local_addr, socket.local_port, remote
+    );
+    if let Some(state) = socket.state {
+        details.push_str(&format!("; state: {}", state));
+    }
+    details.push_str(&format!("; inode: {}", socket.inode));
+    if let Some(uid) = socket.uid {
+        details.push_str(&format!("; uid: {}", uid));
+    }
+    details.push('\n');
+    details.push_str(&internals_details(socket));
+
+    if socket.pids.is_empty() {
+        details.push_str("\nno owning process found (it may belong to another user)");
+        return details;
+    }
+
+    for &pid in &socket.pids {
+        details.push('\n');
+        details.push_str(&process_details(
+            processes,
+            pid,
+            process_throughput.get(&pid).copied(),
+        ));
+    }
+    details
+}
+
+fn internals_details(socket: &SocketRow) -> String {
+    let mut details = String::new();
+    if let Some(queues) = socket.queues {
+        details.push_str(&format!(
+            "recv-q: {}; send-q: {}\n",
+            queues.recv, queues.send
+        ));
+    }
+    if let Some(tcp_info) = socket.tcp_info {
+        details.push_str(&format!(
+            "rtt: {:.3}/{:.3} ms; cwnd: {}; retransmits: {}; lost: {}",
+            f64::from(tcp_info.rtt_us) / 1000.0,
+            f64::from(tcp_info.rttvar_us) / 1000.0,
+            tcp_info.snd_cwnd,
+            tcp_info.retransmits,
+            tcp_info.lost
+        ));
+        if let Some(bytes_acked) = tcp_info.bytes_acked {
+            details.push_str(&format!("; bytes acked: {}", bytes_acked));
+        }
+        if let Some(bytes_received) = tcp_info.bytes_received {
+            details.push_str(&format!("; bytes received: {}", bytes_received));
+        }
+        if let Some(delivery_rate) = tcp_info.delivery_rate {
+            details.push_str(&format!(
+                "; delivery rate: {}/s",
+                format_bytes(delivery_rate)
+            ));
+        }
+        details.push('\n');
+    }
+    if let Some(throughput) = socket.throughput {
+        details.push_str(&format!("throughput: {}\n", format_throughput(throughput)));
+    }
+    if let Some(memory) = socket.memory {
+        details.push_str(&format!(
+            "skmem: rmem_alloc {}, rcvbuf {}, wmem_alloc {}, sndbuf {}, fwd_alloc {}, wmem_queued {}, optmem {}, backlog {}, drops {}\n",
+            memory.rmem_alloc,
+            memory.rcvbuf,
+            memory.wmem_alloc,
+            memory.sndbuf,
+            memory.fwd_alloc,
+            memory.wmem_queued,
+            memory.optmem,
+            memory.backlog,
+            memory.drops
+        ));
+    }
+    details
+}
+
+fn process_details(
+    processes: &HashMap<u32, ProcessInfo>,
+    pid: u32,
+    throughput: Option<Throughput>,
+) -> String {
+    let throughput = throughput
+        .map(|throughput| {
+            format!(
+                "throughput of all sockets: {}\n",
+                format_throughput(throughput)
+            )
+        })
+        .unwrap_or_default();
+    processes
+        .get(&pid)
+        .map(|proc_| {
+            format!(
+                "pid {}::\nname {}\nstatus: {}\ncmd: {:?}\nexe: {:?}\nmemory: {}\nvirtual memory: {}\nstart time: {}\ncpu usage: {}\n{}environ: {:?}\n",
+                pid,
+                proc_.name,
+                proc_.status,
+                proc_.cmd,
+                proc_.exe,
+                proc_.memory,
+                proc_.virtual_memory,
+                proc_.start_time,
+                proc_.cpu_usage,
+                throughput,
+                proc_.environ,
+            )
+        })
+        .unwrap_or_else(|| format!("pid {}:: process is gone\n", pid))
+}
+
+/// A socket shared by several processes counts for each of them.
+fn throughput_by_process(sockets_container: &SocketsContainer) -> HashMap<u32, Throughput> {
+    let mut by_process: HashMap<u32, Throughput> = HashMap::new();
+    for row in sockets_container.rows() {
+        if let Some(throughput) = row.throughput {
+            for &pid in &row.pids {
+                let total = by_process.entry(pid).or_default();
+                *total = *total + throughput;
+            }
+        }
+    }
+    by_process
+}
+
+fn total_throughput(sockets_container: &SocketsContainer) -> Option<Throughput> {
+    let has_counters = sockets_container.rows().any(|row| {
+        row.tcp_info
+            .and_then(|tcp_info| tcp_info.bytes_acked)
+            .is_some()
+    });
+    if !has_counters {
+        return None;
+    }
+    Some(
+        sockets_container
+            .rows()
+            .filter_map(|row| row.throughput)
+            .sum(),
     )
 }
 
+fn container_rows(
+    sockets_info_res: &Result<SocketsContainer, ConnectionToolsError>,
+) -> (
+    &[SocketRow],
+    &[SocketRow],
+    &[UnixSocketRow],
+    &[RawSocketRow],
+) {
+    match sockets_info_res {
+        Ok(sockets_container) => (
+            &sockets_container.tcp_sockets,
+            &sockets_container.udp_sockets,
+            &sockets_container.unix_sockets,
+            &sockets_container.raw_sockets,
+        ),
+        Err(_) => (&[], &[], &[], &[]),
+    }
+}
+
+fn clamp_selection(current: Option<usize>, base_collection_len: usize) -> Option<usize> {
+    match current {
+        _ if base_collection_len == 0 => None,
+        Some(current) if current >= base_collection_len => Some(base_collection_len - 1),
+        current => current,
+    }
+}
+
 fn up_select_counter(current: &Option<usize>, base_collection_len: &usize) -> Option<usize> {
-    if let Some(current) = current.as_ref() {
+    if *base_collection_len == 0 {
+        None
+    } else if let Some(current) = current.as_ref() {
         if *current > 0 {
             Some(*current - 1)
         } else {
@@ -304,7 +1505,9 @@ fn up_select_counter(current: &Option<usize>, base_collection_len: &usize) -> Op
 }
 
 fn down_select_counter(current: &Option<usize>, base_collection_len: &usize) -> Option<usize> {
-    if let Some(current) = current.as_ref() {
+    if *base_collection_len == 0 {
+        None
+    } else if let Some(current) = current.as_ref() {
         if *current >= *base_collection_len - 1 {
             Some(0)
         } else {
@@ -314,3 +1517,337 @@ fn down_select_counter(current: &Option<usize>, base_collection_len: &usize) ->
         Some(0)
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::{App, Highlight, Selection, Tab, HIGHLIGHT_DURATION};
+    use crate::collector::{CollectorEvent, Snapshot};
+    use crate::history::History;
+    use crate::lifecycle::{Change, LifecycleEvent};
+    use crate::recording::Player;
+    use crate::signal::Signal;
+    use crate::sockets::{Protocol, SocketRow, SocketsContainer};
+    use crate::source::SourceKind;
+    use netstat2::TcpState;
+    use std::collections::HashMap;
+    use std::time::{Duration, Instant, SystemTime};
+
+    fn row(local_port: u16) -> SocketRow {
+        let local = format!("127.0.0.1:{}", local_port);
+        SocketRow::test_tcp(&local, "10.0.0.1:443", TcpState::Established)
+    }
+
+    #[test]
+    fn selection_follows_socket() {
+        let rows = vec![row(1), row(2), row(3)];
+        let mut selection = Selection::default();
+        selection.select(Some(1), &rows, &[0, 1, 2]);
+
+        let reordered = vec![row(3), row(1), row(2)];
+        selection.follow(&reordered, &[0, 1, 2]);
+        assert_eq!(selection.index(), Some(2));
+
+        selection.follow(&reordered, &[1, 2]);
+        assert_eq!(selection.index(), Some(1));
+    }
+
+    #[test]
+    fn selection_falls_back_when_socket_disappears() {
+        let rows = vec![row(1), row(2), row(3)];
+        let mut selection = Selection::default();
+        selection.select(Some(2), &rows, &[0, 1, 2]);
+
+        let shrunk = vec![row(1), row(2)];
+        selection.follow(&shrunk, &[0, 1]);
+        assert_eq!(selection.index(), Some(1));
+
+        selection.follow::<SocketRow>(&[], &[]);
+        assert_eq!(selection.index(), None);
+    }
+
+    #[test]
+    fn processes_tab_counts_sockets_per_owner() {
+        let mut listener = row(8080);
+        listener.state = Some(TcpState::Listen);
+        listener.pids = vec![7];
+        let mut client = row(40000);
+        client.pids = vec![7, 9];
+        let mut app = App::new(SourceKind::default());
+        app.apply_snapshot(Snapshot {
+            sockets: Ok(SocketsContainer::from_rows(vec![listener, client, row(1)])),
+            processes: HashMap::new(),
+            taken_at: SystemTime::now(),
+            duration: Duration::from_millis(1),
+            events: Vec::new(),
+        });
+
+        let summaries = app.process_summaries();
+        assert_eq!(summaries.len(), 2);
+        assert_eq!((summaries[0].pid, summaries[0].tcp), (7, 2));
+        assert_eq!(summaries[0].listening, vec![8080]);
+        assert_eq!((summaries[1].pid, summaries[1].tcp), (9, 1));
+        assert_eq!(app.listening_sockets().len(), 1);
+
+        app.on_key('\t');
+        assert_eq!(app.tab(), Tab::Listening);
+        app.on_back_tab();
+        app.on_back_tab();
+        assert_eq!(app.tab(), Tab::Events);
+    }
+
+    #[test]
+    fn new_and_closed_sockets_are_highlighted() {
+        let start = SystemTime::now();
+        let event = |change, local_port| LifecycleEvent {
+            at: start,
+            change,
+            socket: row(local_port),
+        };
+        let mut app = App::new(SourceKind::default());
+        app.apply_snapshot(Snapshot {
+            sockets: Ok(SocketsContainer::from_rows(vec![row(1), row(3), row(4)])),
+            processes: HashMap::new(),
+            taken_at: start,
+            duration: Duration::from_millis(1),
+            events: vec![event(Change::Closed, 2), event(Change::Opened, 4)],
+        });
+        app.on_right();
+        app.on_down();
+        app.on_down();
+
+        let (rows, selected) = app.highlighted_sockets(Protocol::Tcp);
+        let rows: Vec<(u16, Highlight)> = rows
+            .iter()
+            .map(|(row, highlight)| (row.local_port, *highlight))
+            .collect();
+        assert_eq!(
+            rows,
+            vec![
+                (1, Highlight::None),
+                (2, Highlight::Closed),
+                (3, Highlight::None),
+                (4, Highlight::New),
+            ]
+        );
+        assert_eq!(selected, Some(2));
+
+        app.apply_snapshot(Snapshot {
+            sockets: Ok(SocketsContainer::from_rows(vec![row(1), row(3), row(4)])),
+            processes: HashMap::new(),
+            taken_at: start + HIGHLIGHT_DURATION,
+            duration: Duration::from_millis(1),
+            events: Vec::new(),
+        });
+        let (rows, _) = app.highlighted_sockets(Protocol::Tcp);
+        assert_eq!(rows.len(), 3);
+        assert!(rows
+            .iter()
+            .all(|(_, highlight)| *highlight == Highlight::None));
+    }
+
+    #[test]
+    fn replay_steps_and_seeks() {
+        let start = SystemTime::now();
+        let snapshot = |second, ports: &[u16], events| Snapshot {
+            sockets: Ok(SocketsContainer::from_rows(
+                ports.iter().map(|port| row(*port)).collect(),
+            )),
+            processes: HashMap::new(),
+            taken_at: start + Duration::from_secs(second),
+            duration: Duration::from_millis(1),
+            events,
+        };
+        let event = |second, change, local_port| LifecycleEvent {
+            at: start + Duration::from_secs(second),
+            change,
+            socket: row(local_port),
+        };
+        let player = Player::new(
+            SourceKind::default(),
+            vec![
+                snapshot(0, &[1], vec![]),
+                snapshot(1, &[1, 2], vec![event(1, Change::Opened, 2)]),
+                snapshot(2, &[2], vec![event(2, Change::Closed, 1)]),
+            ],
+            Instant::now(),
+        );
+        let mut app = App::replaying(player);
+        assert_eq!((app.tcp_sockets_count, app.events().len()), (1, 0));
+
+        app.on_key('.');
+        assert!(app.replay.as_ref().unwrap().paused);
+        assert_eq!((app.tcp_sockets_count, app.events().len()), (2, 1));
+
+        app.on_key(']');
+        assert_eq!((app.tcp_sockets_count, app.events().len()), (1, 2));
+
+        // going back forgets what happened later
+        app.on_key('[');
+        assert_eq!((app.tcp_sockets_count, app.events().len()), (1, 0));
+        assert_eq!(app.metrics.samples().len(), 1);
+    }
+
+    #[test]
+    fn paused_history_scrubs_back_and_forth() {
+        let start = SystemTime::now();
+        let snapshot = |second, ports: &[u16], events| {
+            CollectorEvent::Snapshot(Box::new(Snapshot {
+                sockets: Ok(SocketsContainer::from_rows(
+                    ports.iter().map(|port| row(*port)).collect(),
+                )),
+                processes: HashMap::new(),
+                taken_at: start + Duration::from_secs(second),
+                duration: Duration::from_millis(1),
+                events,
+            }))
+        };
+        let closed = LifecycleEvent {
+            at: start + Duration::from_secs(20),
+            change: Change::Closed,
+            socket: row(2),
+        };
+        let mut app = App::new(SourceKind::default());
+        app.on_collector_event(snapshot(0, &[1], vec![]));
+        app.on_collector_event(snapshot(10, &[1, 2], vec![]));
+        app.on_collector_event(snapshot(20, &[1], vec![closed]));
+        assert_eq!(app.behind_live(), None);
+        assert_eq!(app.highlighted_sockets(Protocol::Tcp).0.len(), 2);
+
+        app.on_key(' ');
+        assert_eq!(app.behind_live(), Some(Duration::from_secs(0)));
+        app.on_key(',');
+        assert_eq!(app.tcp_sockets_count, 2);
+        assert!(app.events().is_empty());
+        app.on_key('[');
+        assert_eq!(app.behind_live(), Some(Duration::from_secs(20)));
+
+        // live updates go on behind the paused screen
+        app.on_collector_event(snapshot(30, &[], vec![]));
+        assert_eq!(app.tcp_sockets_count, 1);
+        assert_eq!(app.behind_live(), Some(Duration::from_secs(30)));
+        app.on_key(']');
+        app.on_key(']');
+        assert_eq!((app.tcp_sockets_count, app.events().len()), (1, 1));
+
+        app.on_key(' ');
+        assert_eq!(app.behind_live(), None);
+        assert_eq!(app.tcp_sockets_count, 0);
+    }
+
+    #[test]
+    fn paused_view_moves_off_dropped_snapshots() {
+        let start = SystemTime::now();
+        let snapshot = |second, ports: &[u16]| {
+            CollectorEvent::Snapshot(Box::new(Snapshot {
+                sockets: Ok(SocketsContainer::from_rows(
+                    ports.iter().map(|port| row(*port)).collect(),
+                )),
+                processes: HashMap::new(),
+                taken_at: start + Duration::from_secs(second),
+                duration: Duration::from_millis(1),
+                events: vec![],
+            }))
+        };
+        let mut app = App::new(SourceKind::default());
+        app.history = History::new(3, usize::MAX);
+        app.on_collector_event(snapshot(0, &[1]));
+        app.on_collector_event(snapshot(1, &[1, 2]));
+        app.on_collector_event(snapshot(2, &[1, 2, 3]));
+        app.on_key(' ');
+        app.on_key(',');
+        assert_eq!(app.tcp_sockets_count, 2);
+
+        // still kept, one step closer to the oldest
+        app.on_collector_event(snapshot(3, &[]));
+        assert_eq!(app.tcp_sockets_count, 2);
+        assert_eq!(app.behind_live(), Some(Duration::from_secs(2)));
+
+        app.on_collector_event(snapshot(4, &[]));
+        assert_eq!(app.tcp_sockets_count, 3);
+        assert_eq!(app.behind_live(), Some(Duration::from_secs(2)));
+    }
+
+    #[test]
+    fn refresh_interval_steps() {
+        let mut app = App::new(SourceKind::default());
+        app.refresh_interval = Duration::from_millis(300);
+        app.on_key('+');
+        assert_eq!(app.refresh_interval, Duration::from_millis(250));
+        app.on_key('+');
+        app.on_key('+');
+        assert_eq!(app.refresh_interval, Duration::from_millis(100));
+
+        app.refresh_interval = Duration::from_millis(3000);
+        app.on_key('-');
+        assert_eq!(app.refresh_interval, Duration::from_secs(5));
+        for _ in 0..5 {
+            app.on_key('-');
+        }
+        assert_eq!(app.refresh_interval, Duration::from_secs(30));
+    }
+
+    #[test]
+    fn refresh_interval_clamps() {
+        let mut app = App::new(SourceKind::default());
+        app.refresh_interval = Duration::from_millis(100);
+        app.on_key('+');
+        assert_eq!(app.refresh_interval, Duration::from_millis(100));
+        app.on_key('-');
+        assert_eq!(app.refresh_interval, Duration::from_millis(250));
+
+        app.refresh_interval = Duration::from_secs(30);
+        app.on_key('-');
+        assert_eq!(app.refresh_interval, Duration::from_secs(30));
+        app.on_key('+');
+        assert_eq!(app.refresh_interval, Duration::from_secs(10));
+    }
+
+    #[test]
+    fn kill_dialog_picks_a_signal() {
+        let mut owned = row(2);
+        owned.pids = vec![std::process::id()];
+        let mut app = App::new(SourceKind::default());
+        app.apply_snapshot(Snapshot {
+            sockets: Ok(SocketsContainer::from_rows(vec![row(1), owned])),
+            processes: HashMap::new(),
+            taken_at: SystemTime::now(),
+            duration: Duration::from_millis(1),
+            events: Vec::new(),
+        });
+
+        app.on_key('k');
+        assert!(app.kill_dialog.is_none());
+        app.on_right();
+        app.on_key('k');
+        assert!(app.kill_dialog.is_none());
+        let err = app.last_signal.as_ref().unwrap().as_ref().unwrap_err();
+        assert!(err.to_string().ends_with("select a socket first"));
+
+        app.on_down();
+        app.on_key('k');
+        let err = app.last_signal.as_ref().unwrap().as_ref().unwrap_err();
+        assert!(err.to_string().contains("no running owner"));
+
+        app.on_down();
+        app.on_key('k');
+        let dialog = app.kill_dialog.as_ref().unwrap();
+        assert_eq!(dialog.socket, "TCP 127.0.0.1:2 -> 10.0.0.1:443");
+        assert_eq!(dialog.owners[0].0.pid, std::process::id());
+        assert!(app.last_signal.is_none());
+
+        app.on_right();
+        app.on_key('\t');
+        app.on_left();
+        assert_eq!(app.kill_dialog.as_ref().unwrap().signal, Signal::Kill);
+        app.on_esc();
+        assert!(app.kill_dialog.is_none());
+        assert!(app.last_signal.is_none());
+
+        // poke refuses to signal itself, and says so
+        app.on_key('k');
+        app.on_key('\n');
+        assert!(app.kill_dialog.is_none());
+        let err = app.last_signal.as_ref().unwrap().as_ref().unwrap_err();
+        assert!(err.to_string().ends_with("that is poke itself"));
+    }
+}
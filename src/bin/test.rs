@@ -1,6 +1,7 @@
 #[derive(Debug)]
+#[allow(dead_code)]
 struct NotCopyable {
-    x: i32
+    x: i32,
 }
 #[derive(Debug)]
 struct TwoFields {
@@ -10,19 +11,17 @@ struct TwoFields {
 
 pub fn main() {
     let two_fields = TwoFields {
-        first: NotCopyable {x: 22} ,
-        second: NotCopyable {x: 44} ,
-
+        first: NotCopyable { x: 22 },
+        second: NotCopyable { x: 44 },
     };
 
-
     let mut vector_of_tuples: Vec<(NotCopyable, NotCopyable)> = Vec::new();
 
-   match two_fields {
-       TwoFields {first: f, second: s} => vector_of_tuples.push((f, s)),
-   } ;
+    let TwoFields {
+        first: f,
+        second: s,
+    } = two_fields;
+    vector_of_tuples.push((f, s));
 
     println!("{:?}", vector_of_tuples);
 }
-
-
@@ -0,0 +1,550 @@
+//! Command line: the TUI by default, or a one-shot listing for scripts and
+//! non-interactive sessions.
+//!
+//! The listings exit like `grep`: 0 when some socket matched, 1 when none
+//! did and 2 on bad usage or when the sockets can't be read.
+
+use std::ffi::OsString;
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::thread;
+use std::time::Duration;
+
+use clap::{App, AppSettings, Arg, ArgMatches, ErrorKind, SubCommand};
+use netstat2::TcpState;
+
+use crate::collector::SnapshotBuilder;
+use crate::errors::ConnectionToolsError;
+use crate::export::{self, EventRecord, Format, SocketRecord};
+use crate::filter::Filter;
+use crate::format::{display_or_star, format_clock, format_duration, format_endpoint, join_pids};
+use crate::lifecycle::{Change, LifecycleEvent};
+use crate::sockets::SocketRow;
+use crate::sorting::Sorting;
+use crate::source::SourceKind;
+use crate::util::event::Config;
+
+pub const EXIT_FOUND: i32 = 0;
+pub const EXIT_NOT_FOUND: i32 = 1;
+pub const EXIT_ERROR: i32 = 2;
+
+const MIN_INTERVAL: Duration = Duration::from_millis(10);
+
+pub struct Options {
+    pub source: SourceKind,
+    /// `None` prints the listings as aligned tables; the TUI exports JSON.
+    pub format: Option<Format>,
+    /// Time between two snapshots of the TUI and of `watch`.
+    pub interval: Duration,
+    pub command: Command,
+}
+
+pub enum Command {
+    Tui(Session),
+    /// Prints the TCP and UDP sockets matching the query and exits.
+    List(Query),
+    /// Prints the sockets matching the filter as they are opened, closed or
+    /// change state, until interrupted.
+    Watch(Filter),
+}
+
+pub enum Session {
+    /// Collects snapshots, and writes them to the file if there is one.
+    Live { record: Option<PathBuf> },
+    /// Plays a recording back.
+    Replay(PathBuf),
+}
+
+pub struct Query {
+    /// Only listening TCP and bound UDP sockets.
+    pub listening: bool,
+    pub filter: Filter,
+}
+
+const LIST_COLUMNS: [&str; 6] = [
+    "Proto",
+    "Local address",
+    "Remote address",
+    "State",
+    "PIDs",
+    "Process",
+];
+
+fn command_line() -> App<'static, 'static> {
+    let filter = Arg::with_name("filter")
+        .multiple(true)
+        .help("Filter terms, as typed after / in the TUI (port:5432 proc:postgres !lo)");
+    App::new("poke")
+        .version(env!("CARGO_PKG_VERSION"))
+        .about("Monitors the sockets of the system")
+        .setting(AppSettings::VersionlessSubcommands)
+        .arg(
+            Arg::with_name("source")
+                .long("source")
+                .takes_value(true)
+                .value_name("NAME")
+                .possible_values(
+                    &SourceKind::ALL
+                        .iter()
+                        .map(|kind| kind.name())
+                        .collect::<Vec<_>>(),
+                )
+                .global(true)
+                .help("Backend that lists the TCP and UDP sockets"),
+        )
+        .arg(
+            Arg::with_name("format")
+                .long("format")
+                .takes_value(true)
+                .value_name("FORMAT")
+                .possible_values(
+                    &Format::ALL
+                        .iter()
+                        .map(|format| format.name())
+                        .collect::<Vec<_>>(),
+                )
+                .global(true)
+                .help("Prints the listings in this format, and is the format of the TUI exports"),
+        )
+        .arg(
+            Arg::with_name("interval")
+                .long("interval")
+                .takes_value(true)
+                .value_name("MS")
+                .global(true)
+                .help("Milliseconds between two refreshes of the TUI or of watch [default: 250]"),
+        )
+        .arg(
+            Arg::with_name("record")
+                .long("record")
+                .takes_value(true)
+                .value_name("FILE")
+                .conflicts_with("replay")
+                .help("Writes every snapshot of the TUI to FILE"),
+        )
+        .arg(
+            Arg::with_name("replay")
+                .long("replay")
+                .takes_value(true)
+                .value_name("FILE")
+                .help("Plays back a recording in the TUI instead of listing sockets"),
+        )
+        .subcommand(
+            SubCommand::with_name("list")
+                .about("Prints all TCP and UDP sockets")
+                .arg(filter.clone()),
+        )
+        .subcommand(
+            SubCommand::with_name("listen")
+                .about("Prints listening TCP and bound UDP sockets")
+                .arg(filter.clone()),
+        )
+        .subcommand(
+            SubCommand::with_name("watch")
+                .about("Prints a line per socket opened, closed or changing state")
+                .arg(filter),
+        )
+        .subcommand(
+            SubCommand::with_name("port")
+                .about("Prints the sockets with a local or remote port")
+                .arg(Arg::with_name("port").required(true)),
+        )
+        .subcommand(
+            SubCommand::with_name("pid")
+                .about("Prints the sockets owned by a process")
+                .arg(Arg::with_name("pid").required(true)),
+        )
+}
+
+pub fn parse<I: IntoIterator<Item = OsString>>(args: I) -> Result<Options, clap::Error> {
+    let matches = command_line().get_matches_from_safe(args)?;
+    let source = match matches.value_of("source") {
+        Some(name) => name.parse().map_err(invalid_value)?,
+        None => SourceKind::default(),
+    };
+    let format = match matches.value_of("format") {
+        Some(name) => Some(name.parse().map_err(invalid_value)?),
+        None => None,
+    };
+    let interval = match matches.value_of("interval") {
+        Some(_) => Duration::from_millis(number(&matches, "interval")?),
+        None => Config::default().tick_rate,
+    };
+    if interval < MIN_INTERVAL {
+        return Err(clap::Error::with_description(
+            &format!("interval must be at least {} ms", MIN_INTERVAL.as_millis()),
+            ErrorKind::InvalidValue,
+        ));
+    }
+    let command = match matches.subcommand() {
+        ("list", Some(args)) => Command::List(Query {
+            listening: false,
+            filter: filter(args)?,
+        }),
+        ("listen", Some(args)) => Command::List(Query {
+            listening: true,
+            filter: filter(args)?,
+        }),
+        ("port", Some(args)) => {
+            let port = number::<u16>(args, "port")?;
+            Command::List(Query {
+                listening: false,
+                filter: Filter::parse(&format!("port:{}", port)).map_err(invalid_value)?,
+            })
+        }
+        ("pid", Some(args)) => {
+            let pid = number::<u32>(args, "pid")?;
+            Command::List(Query {
+                listening: false,
+                filter: Filter::parse(&format!("pid:{}", pid)).map_err(invalid_value)?,
+            })
+        }
+        ("watch", Some(args)) => {
+            if format == Some(Format::Csv) {
+                return Err(clap::Error::with_description(
+                    "watch prints text or NDJSON",
+                    ErrorKind::InvalidValue,
+                ));
+            }
+            Command::Watch(filter(args)?)
+        }
+        _ => Command::Tui(
+            match (matches.value_of_os("record"), matches.value_of_os("replay")) {
+                (_, Some(replay)) => Session::Replay(replay.into()),
+                (record, None) => Session::Live {
+                    record: record.map(PathBuf::from),
+                },
+            },
+        ),
+    };
+    let session_args = matches.is_present("record") || matches.is_present("replay");
+    if session_args && !matches!(command, Command::Tui(_)) {
+        return Err(clap::Error::with_description(
+            "--record and --replay only apply to the TUI",
+            ErrorKind::ArgumentConflict,
+        ));
+    }
+    Ok(Options {
+        source,
+        format,
+        interval,
+        command,
+    })
+}
+
+fn filter(args: &ArgMatches) -> Result<Filter, clap::Error> {
+    let terms: Vec<&str> = args.values_of("filter").into_iter().flatten().collect();
+    Filter::parse(&terms.join(" ")).map_err(invalid_value)
+}
+
+fn number<T: std::str::FromStr>(args: &ArgMatches, name: &str) -> Result<T, clap::Error> {
+    let value = args.value_of(name).unwrap_or_default();
+    value.parse().map_err(|_| {
+        clap::Error::with_description(
+            &format!("invalid {} '{}'", name, value),
+            ErrorKind::InvalidValue,
+        )
+    })
+}
+
+fn invalid_value(err: ConnectionToolsError) -> clap::Error {
+    clap::Error::with_description(&err.to_string(), ErrorKind::InvalidValue)
+}
+
+/// Collects one snapshot and prints the matching sockets to stdout; returns
+/// the exit code.
+pub fn run(source: SourceKind, format: Option<Format>, query: &Query) -> i32 {
+    let snapshot = SnapshotBuilder::new(source.create()).collect();
+    let sockets = match &snapshot.sockets {
+        Ok(sockets) => sockets,
+        Err(err) => {
+            eprintln!("poke: {}", err);
+            return EXIT_ERROR;
+        }
+    };
+    let mut rows: Vec<SocketRow> = sockets
+        .rows()
+        .filter(|row| !query.listening || is_listening(row))
+        .filter(|row| query.filter.matches(row))
+        .cloned()
+        .collect();
+    Sorting::new().apply(&mut rows);
+
+    let stdout = io::stdout();
+    let written = match format {
+        Some(format) => {
+            let records: Vec<SocketRecord> = rows
+                .iter()
+                .map(|row| SocketRecord::new(row, &snapshot.processes, snapshot.taken_at))
+                .collect();
+            export::write(&mut stdout.lock(), format, &records)
+        }
+        None => write_table(&mut stdout.lock(), &rows),
+    };
+    if let Err(err) = written {
+        // a closed pipe, as with `poke list | head`, is not worth a message
+        if err.kind() != io::ErrorKind::BrokenPipe {
+            eprintln!("poke: {}", err);
+            return EXIT_ERROR;
+        }
+    }
+    if rows.is_empty() {
+        EXIT_NOT_FOUND
+    } else {
+        EXIT_FOUND
+    }
+}
+
+/// Prints the events of the sockets matching `filter`, as text or as NDJSON
+/// with `format`, until stdout is closed; returns the exit code.
+pub fn watch(
+    source: SourceKind,
+    format: Option<Format>,
+    filter: &Filter,
+    interval: Duration,
+) -> i32 {
+    let mut builder = SnapshotBuilder::new(source.create());
+    if source == SourceKind::SockDiag {
+        // sockets living shorter than the interval are only missed then
+        if let Err(err) = builder.watch_destroyed() {
+            eprintln!("poke: {}", err);
+        }
+    }
+    let stdout = io::stdout();
+    loop {
+        let snapshot = builder.collect();
+        if let Err(err) = &snapshot.sockets {
+            eprintln!("poke: {}", err);
+            return EXIT_ERROR;
+        }
+        let mut out = stdout.lock();
+        for event in &snapshot.events {
+            if !filter.matches(&event.socket) {
+                continue;
+            }
+            let written = match format {
+                Some(_) => {
+                    let record = EventRecord::new(event, &snapshot.processes);
+                    serde_json::to_writer(&mut out, &record)
+                        .map_err(io::Error::from)
+                        .and_then(|_| writeln!(out))
+                }
+                None => writeln!(out, "{}", event_line(event)),
+            };
+            match written {
+                Ok(()) => {}
+                Err(err) if err.kind() == io::ErrorKind::BrokenPipe => return EXIT_FOUND,
+                Err(err) => {
+                    eprintln!("poke: {}", err);
+                    return EXIT_ERROR;
+                }
+            }
+        }
+        drop(out);
+        thread::sleep(interval.checked_sub(snapshot.duration).unwrap_or_default());
+    }
+}
+
+/// `12:00:01 CLOSED TCP 10.0.0.2:5000 -> 10.0.0.1:443 ESTABLISHED lived 4s pid 42 (curl)`
+fn event_line(event: &LifecycleEvent) -> String {
+    let socket = &event.socket;
+    let mut line = format!(
+        "{} {:6} {:3} {}",
+        format_clock(event.at),
+        event.change.to_string(),
+        socket.protocol,
+        format_endpoint(socket.local_addr, socket.local_port)
+    );
+    if let (Some(remote_addr), Some(remote_port)) = (socket.remote_addr, socket.remote_port) {
+        line.push_str(&format!(
+            " -> {}",
+            format_endpoint(remote_addr, remote_port)
+        ));
+    }
+    match (event.change, socket.state) {
+        (Change::StateChanged { from, to }, _) => line.push_str(&format!(" {} → {}", from, to)),
+        (_, Some(state)) => line.push_str(&format!(" {}", state)),
+        (_, None) => {}
+    }
+    if let Some(lived) = event.lived() {
+        line.push_str(&format!(" lived {}", format_duration(lived)));
+    }
+    if !socket.pids.is_empty() {
+        line.push_str(&format!(" pid {}", join_pids(&socket.pids)));
+    }
+    if let Some(process_name) = &socket.process_name {
+        line.push_str(&format!(" ({})", process_name));
+    }
+    line
+}
+
+/// Listening TCP sockets and every UDP socket, as in the Listening tab.
+fn is_listening(row: &SocketRow) -> bool {
+    row.remote_port.is_none() || row.state == Some(TcpState::Listen)
+}
+
+fn list_cells(row: &SocketRow) -> Vec<String> {
+    let remote = match (row.remote_addr, row.remote_port) {
+        (Some(remote_addr), Some(remote_port)) => format_endpoint(remote_addr, remote_port),
+        _ => "*".to_owned(),
+    };
+    vec![
+        row.protocol.to_string(),
+        format_endpoint(row.local_addr, row.local_port),
+        remote,
+        display_or_star(&row.state),
+        join_pids(&row.pids),
+        row.process_name.clone().unwrap_or_default(),
+    ]
+}
+
+/// Columns padded to their widest cell, like `ss` and `netstat` print them.
+fn write_table<W: Write>(out: &mut W, rows: &[SocketRow]) -> io::Result<()> {
+    let cells: Vec<Vec<String>> = rows.iter().map(list_cells).collect();
+    let mut widths: Vec<usize> = LIST_COLUMNS.iter().map(|title| title.len()).collect();
+    for row in &cells {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+
+    let header: Vec<String> = LIST_COLUMNS.iter().map(|title| title.to_string()).collect();
+    for line in std::iter::once(&header).chain(&cells) {
+        let padded: Vec<String> = line
+            .iter()
+            .zip(&widths)
+            .map(|(cell, width)| format!("{:width$}", cell, width = width))
+            .collect();
+        writeln!(out, "{}", padded.join("  ").trim_end())?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::{event_line, parse, write_table, Command, Session};
+    use crate::export::Format;
+    use crate::lifecycle::{Change, LifecycleEvent};
+    use crate::sockets::SocketRow;
+    use crate::source::SourceKind;
+    use netstat2::TcpState;
+    use std::ffi::OsString;
+    use std::path::PathBuf;
+    use std::time::Duration;
+
+    fn args(line: &str) -> Vec<OsString> {
+        line.split_whitespace().map(OsString::from).collect()
+    }
+
+    #[test]
+    fn subcommands() {
+        let options = parse(args("poke")).unwrap();
+        assert!(matches!(
+            options.command,
+            Command::Tui(Session::Live { record: None })
+        ));
+        assert_eq!(options.source, SourceKind::default());
+
+        let options = parse(args("poke --source procfs listen --format csv")).unwrap();
+        assert_eq!(options.source, SourceKind::Procfs);
+        assert_eq!(options.format, Some(Format::Csv));
+        match options.command {
+            Command::List(query) => assert!(query.listening && query.filter.is_empty()),
+            _ => panic!("listen lists sockets"),
+        }
+        match parse(args("poke list proc:nginx !lo")).unwrap().command {
+            Command::List(query) => assert!(!query.listening && !query.filter.is_empty()),
+            _ => panic!("list lists sockets"),
+        }
+        match parse(args("poke watch --format ndjson port:443"))
+            .unwrap()
+            .command
+        {
+            Command::Watch(filter) => assert!(!filter.is_empty()),
+            _ => panic!("watch streams events"),
+        }
+
+        assert!(parse(args("poke port 8080")).is_ok());
+        assert!(parse(args("poke port http")).is_err());
+        assert!(parse(args("poke pid")).is_err());
+        assert!(parse(args("poke list port:")).is_err());
+        assert!(parse(args("poke --source ss list")).is_err());
+        assert!(parse(args("poke list --format xml")).is_err());
+        assert!(parse(args("poke watch --format csv")).is_err());
+
+        match parse(args("poke --record incident.ndjson.gz"))
+            .unwrap()
+            .command
+        {
+            Command::Tui(Session::Live { record }) => {
+                assert_eq!(record, Some(PathBuf::from("incident.ndjson.gz")))
+            }
+            _ => panic!("--record records the TUI"),
+        }
+        assert!(matches!(
+            parse(args("poke --replay incident.ndjson.gz"))
+                .unwrap()
+                .command,
+            Command::Tui(Session::Replay(_))
+        ));
+        assert!(parse(args("poke --record a --replay b")).is_err());
+        assert!(parse(args("poke --record a list")).is_err());
+
+        assert_eq!(
+            parse(args("poke")).unwrap().interval,
+            Duration::from_millis(250)
+        );
+        assert_eq!(
+            parse(args("poke watch --interval 1000")).unwrap().interval,
+            Duration::from_secs(1)
+        );
+        assert!(parse(args("poke --interval 0")).is_err());
+        assert!(parse(args("poke --interval 1s")).is_err());
+    }
+
+    fn row() -> SocketRow {
+        SocketRow {
+            pids: vec![42],
+            inode: 1,
+            process_name: Some("nginx".to_owned()),
+            ..SocketRow::test_tcp("127.0.0.1:8080", "0.0.0.0:0", TcpState::Listen)
+        }
+    }
+
+    #[test]
+    fn aligned_table() {
+        let mut out = Vec::new();
+
+        write_table(&mut out, &[row()]).unwrap();
+
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "Proto  Local address   Remote address  State   PIDs  Process\n\
+             TCP    127.0.0.1:8080  0.0.0.0:0       LISTEN  42    nginx\n"
+        );
+    }
+
+    #[test]
+    fn event_lines() {
+        let socket = row();
+        let at = socket.first_seen + Duration::from_secs(4);
+        let closed = LifecycleEvent {
+            at,
+            change: Change::Closed,
+            socket: socket.clone(),
+        };
+        assert!(event_line(&closed)
+            .ends_with(" CLOSED TCP 127.0.0.1:8080 -> 0.0.0.0:0 LISTEN lived 4s pid 42 (nginx)"));
+
+        let changed = LifecycleEvent {
+            at,
+            change: Change::StateChanged {
+                from: TcpState::SynSent,
+                to: TcpState::Established,
+            },
+            socket,
+        };
+        assert!(event_line(&changed)
+            .contains(" STATE  TCP 127.0.0.1:8080 -> 0.0.0.0:0 SYN_SENT → ESTABLISHED pid"));
+    }
+}
@@ -0,0 +1,253 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::mpsc;
+use std::thread;
+use std::time::{Duration, Instant, SystemTime};
+
+use serde::{Deserialize, Serialize};
+
+use crate::errors::ConnectionToolsError;
+use crate::lifecycle::{LifecycleEvent, Tracker};
+use crate::processes::{ProcessCache, ProcessInfo};
+use crate::recording::Recorder;
+use crate::sockets::{ByteCounters, SocketId, SocketRow, SocketsContainer};
+use crate::source::{self, DestroyListener, SocketOwners, SocketSource};
+
+/// How long the owners of the Unix and raw sockets are reused for when the
+/// source scans `/proc` for the TCP and UDP owners on its own.
+const OWNERS_MAX_AGE: Duration = Duration::from_secs(2);
+
+/// Everything poke knows about the system at one point in time.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Snapshot {
+    pub sockets: Result<SocketsContainer, ConnectionToolsError>,
+    pub processes: HashMap<u32, ProcessInfo>,
+    pub taken_at: SystemTime,
+    /// How long it took to collect the snapshot.
+    pub duration: Duration,
+    /// Changes since the previous snapshot.
+    pub events: Vec<LifecycleEvent>,
+}
+
+pub enum CollectorEvent {
+    /// Whether destroyed sockets are reported as the kernel destroys them.
+    WatchingDestroyed(Result<(), ConnectionToolsError>),
+    /// A new snapshot is being collected.
+    Refreshing,
+    Snapshot(Box<Snapshot>),
+    /// Writing to the recording failed; nothing more is recorded.
+    RecordingFailed(ConnectionToolsError),
+}
+
+/// Collects snapshots on its own thread so that slow collection never blocks
+/// key handling or drawing.
+pub struct Collector {
+    rx: mpsc::Receiver<CollectorEvent>,
+    interval_tx: mpsc::Sender<Duration>,
+    #[allow(dead_code)]
+    handle: thread::JoinHandle<()>,
+}
+
+impl Collector {
+    pub fn spawn(
+        interval: Duration,
+        source: Box<dyn SocketSource>,
+        watch_destroyed: bool,
+        mut recorder: Option<Recorder>,
+    ) -> Collector {
+        let (tx, rx) = mpsc::channel();
+        let (interval_tx, interval_rx) = mpsc::channel();
+        let handle = thread::spawn(move || {
+            let mut interval = interval;
+            let mut builder = SnapshotBuilder::new(source);
+            if watch_destroyed {
+                let watching = builder.watch_destroyed();
+                if tx
+                    .send(CollectorEvent::WatchingDestroyed(watching))
+                    .is_err()
+                {
+                    return;
+                }
+            }
+            loop {
+                if tx.send(CollectorEvent::Refreshing).is_err() {
+                    return;
+                }
+                let started = Instant::now();
+                let snapshot = builder.collect();
+                if let Some(Err(err)) = recorder.as_mut().map(|recorder| recorder.record(&snapshot))
+                {
+                    recorder = None;
+                    if tx.send(CollectorEvent::RecordingFailed(err)).is_err() {
+                        return;
+                    }
+                }
+                if tx
+                    .send(CollectorEvent::Snapshot(Box::new(snapshot)))
+                    .is_err()
+                {
+                    return;
+                }
+                // a new interval counts from the start of this collection
+                while let Some(wait) = interval.checked_sub(started.elapsed()) {
+                    match interval_rx.recv_timeout(wait) {
+                        Ok(new_interval) => interval = new_interval,
+                        Err(mpsc::RecvTimeoutError::Timeout) => break,
+                        Err(mpsc::RecvTimeoutError::Disconnected) => return,
+                    }
+                }
+            }
+        });
+
+        Collector {
+            rx,
+            interval_tx,
+            handle,
+        }
+    }
+
+    pub fn set_interval(&self, interval: Duration) {
+        let _ = self.interval_tx.send(interval);
+    }
+
+    pub fn try_next(&self) -> Option<CollectorEvent> {
+        self.rx.try_recv().ok()
+    }
+}
+
+/// State that has to survive between two snapshots.
+pub struct SnapshotBuilder {
+    source: Box<dyn SocketSource>,
+    processes: ProcessCache,
+    first_seen: HashMap<SocketId, SystemTime>,
+    byte_counters: ByteCounters,
+    last_collected: Option<Instant>,
+    tracker: Tracker,
+    /// The last `socket_owners` scan, with when it was done.
+    owners: Option<(Instant, SocketOwners)>,
+    /// Sockets destroyed since the last snapshot, with the time they were.
+    destroyed: Option<mpsc::Receiver<(SystemTime, SocketRow)>>,
+}
+
+impl SnapshotBuilder {
+    pub fn new(source: Box<dyn SocketSource>) -> Self {
+        SnapshotBuilder {
+            source,
+            processes: ProcessCache::new(Duration::from_secs(2)),
+            first_seen: HashMap::new(),
+            byte_counters: ByteCounters::new(),
+            last_collected: None,
+            tracker: Tracker::new(),
+            owners: None,
+            destroyed: None,
+        }
+    }
+
+    /// Listens for sockets destroyed by the kernel on a thread of its own,
+    /// so that sockets living shorter than a refresh are reported too.
+    pub fn watch_destroyed(&mut self) -> Result<(), ConnectionToolsError> {
+        let listener = DestroyListener::open()?;
+        let (tx, rx) = mpsc::channel();
+        thread::spawn(move || {
+            // the owners are gone by the time a socket is destroyed
+            let owners = SocketOwners::new();
+            while let Ok(rows) = listener.next(&owners) {
+                for row in rows {
+                    if tx.send((row.first_seen, row)).is_err() {
+                        return;
+                    }
+                }
+            }
+        });
+        self.destroyed = Some(rx);
+        Ok(())
+    }
+
+    pub fn collect(&mut self) -> Snapshot {
+        let started = Instant::now();
+        let taken_at = SystemTime::now();
+
+        let (scanned_at, owners) = match self.owners.take() {
+            Some((scanned_at, owners))
+                if self.source.finds_owners()
+                    && started.duration_since(scanned_at) < OWNERS_MAX_AGE =>
+            {
+                (scanned_at, owners)
+            }
+            _ => (started, source::socket_owners()),
+        };
+        let mut sockets = self
+            .source
+            .collect(taken_at, &owners)
+            .map(SocketsContainer::from_rows);
+
+        let mut events = Vec::new();
+        if let Ok(current) = &mut sockets {
+            // Unix, raw, packet and netlink sockets are listed on top of the
+            // chosen source; when they can't be read the TCP and UDP panes
+            // are still worth showing
+            current.unix_sockets = source::collect_unix(taken_at, &owners).unwrap_or_default();
+            current.raw_sockets = source::collect_raw(taken_at, &owners).unwrap_or_default();
+            current.carry_first_seen(&self.first_seen);
+            self.first_seen = current.first_seen();
+            if let Some(last_collected) = self.last_collected {
+                current.compute_throughput(&self.byte_counters, started - last_collected);
+            }
+            self.byte_counters = current.byte_counters();
+            self.last_collected = Some(started);
+
+            let pids: HashSet<u32> = current
+                .rows()
+                .flat_map(|row| row.pids.iter().copied())
+                .chain(
+                    current
+                        .unix_sockets
+                        .iter()
+                        .flat_map(|row| row.pids.iter().copied()),
+                )
+                .chain(
+                    current
+                        .raw_sockets
+                        .iter()
+                        .flat_map(|row| row.pids.iter().copied()),
+                )
+                .collect();
+            self.processes.update(&pids);
+            fill_process_names(&self.processes, current);
+            if let Some(destroyed) = &self.destroyed {
+                for (at, row) in destroyed.try_iter() {
+                    events.push(self.tracker.destroyed(row, at));
+                }
+            }
+            events.extend(self.tracker.diff(current, taken_at));
+        }
+        self.owners = Some((scanned_at, owners));
+
+        Snapshot {
+            sockets,
+            processes: self.processes.snapshot(),
+            taken_at,
+            duration: started.elapsed(),
+            events,
+        }
+    }
+}
+
+fn fill_process_names(processes: &ProcessCache, sockets_container: &mut SocketsContainer) {
+    let name = |pids: &[u32]| {
+        pids.iter()
+            .find_map(|&pid| processes.name(pid).map(str::to_owned))
+    };
+    for row in sockets_container
+        .tcp_sockets
+        .iter_mut()
+        .chain(sockets_container.udp_sockets.iter_mut())
+    {
+        row.process_name = name(&row.pids);
+    }
+    for row in &mut sockets_container.unix_sockets {
+        row.process_name = name(&row.pids);
+    }
+    for row in &mut sockets_container.raw_sockets {
+        row.process_name = name(&row.pids);
+    }
+}
@@ -1,7 +1,25 @@
-#[derive(Debug, Fail)]
+#![allow(non_local_definitions)]
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Fail, Serialize, Deserialize)]
 pub enum ConnectionToolsError {
     #[fail(display = "fail to get sockets info: {}", message)]
     FailToGetSocketsInfo { message: String },
+    #[fail(display = "invalid filter: {}", message)]
+    InvalidFilter { message: String },
+    #[fail(display = "unknown socket source: {}", name)]
+    UnknownSource { name: String },
+    #[fail(display = "can't watch destroyed sockets: {}", message)]
+    WatchDestroyed { message: String },
+    #[fail(display = "can't export: {}", message)]
+    Export { message: String },
+    #[fail(display = "can't record: {}", message)]
+    Record { message: String },
+    #[fail(display = "can't replay: {}", message)]
+    Replay { message: String },
+    #[fail(display = "can't send signal: {}", message)]
+    Signal { message: String },
 }
 
 #[cfg(test)]
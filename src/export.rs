@@ -0,0 +1,497 @@
+//! Sockets, with their owning processes, as JSON, newline delimited JSON or
+//! CSV.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::fs::OpenOptions;
+use std::io::{self, BufWriter, Write};
+use std::str::FromStr;
+use std::time::SystemTime;
+
+use serde::Serialize;
+
+use crate::errors::ConnectionToolsError;
+use crate::format::unix_seconds;
+use crate::lifecycle::{Change, LifecycleEvent};
+use crate::processes::ProcessInfo;
+use crate::sockets::{RawSocketRow, SocketRow, UnixSocketRow};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    /// One array of all the sockets.
+    Json,
+    /// One object per line.
+    Ndjson,
+    Csv,
+}
+
+impl Format {
+    pub const ALL: [Format; 3] = [Format::Json, Format::Ndjson, Format::Csv];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Format::Json => "json",
+            Format::Ndjson => "ndjson",
+            Format::Csv => "csv",
+        }
+    }
+}
+
+impl fmt::Display for Format {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+impl FromStr for Format {
+    type Err = ConnectionToolsError;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        Format::ALL
+            .iter()
+            .copied()
+            .find(|format| format.name() == name)
+            .ok_or_else(|| ConnectionToolsError::Export {
+                message: format!("unknown format {}", name),
+            })
+    }
+}
+
+/// One line of NDJSON or CSV.
+pub trait Record: Serialize {
+    /// The CSV header.
+    const COLUMNS: &'static [&'static str];
+
+    /// Cells in the order of `COLUMNS`.
+    fn csv_cells(&self) -> Vec<String>;
+}
+
+/// A socket flattened for export; missing values are `null` in JSON and
+/// empty in CSV.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SocketRecord {
+    /// Seconds since the Unix epoch of the snapshot.
+    pub taken_at: f64,
+    pub protocol: String,
+    pub family: String,
+    pub local_addr: String,
+    pub local_port: u16,
+    pub remote_addr: Option<String>,
+    pub remote_port: Option<u16>,
+    pub state: Option<String>,
+    pub pids: Vec<u32>,
+    pub uid: Option<u32>,
+    pub inode: u32,
+    pub process: Option<String>,
+    /// Command line of the first owner poke knows about.
+    pub command: Option<String>,
+    pub exe: Option<String>,
+    pub first_seen: f64,
+    pub recv_queue: Option<u32>,
+    pub send_queue: Option<u32>,
+    pub rtt_ms: Option<f64>,
+    pub retransmits: Option<u32>,
+    pub bytes_received: Option<u64>,
+    pub bytes_sent: Option<u64>,
+    /// Bytes per second since the previous snapshot.
+    pub recv_rate: Option<u64>,
+    pub send_rate: Option<u64>,
+}
+
+const SOCKET_COLUMNS: [&str; 23] = [
+    "taken_at",
+    "protocol",
+    "family",
+    "local_addr",
+    "local_port",
+    "remote_addr",
+    "remote_port",
+    "state",
+    "pids",
+    "uid",
+    "inode",
+    "process",
+    "command",
+    "exe",
+    "first_seen",
+    "recv_queue",
+    "send_queue",
+    "rtt_ms",
+    "retransmits",
+    "bytes_received",
+    "bytes_sent",
+    "recv_rate",
+    "send_rate",
+];
+
+impl SocketRecord {
+    pub fn new(
+        row: &SocketRow,
+        processes: &HashMap<u32, ProcessInfo>,
+        taken_at: SystemTime,
+    ) -> Self {
+        let owner = row.pids.iter().find_map(|pid| processes.get(pid));
+        SocketRecord {
+            taken_at: unix_seconds(taken_at),
+            protocol: row.protocol.to_string(),
+            family: row.family.to_string(),
+            local_addr: row.local_addr.to_string(),
+            local_port: row.local_port,
+            remote_addr: row.remote_addr.map(|addr| addr.to_string()),
+            remote_port: row.remote_port,
+            state: row.state.map(|state| state.to_string()),
+            pids: row.pids.clone(),
+            uid: row.uid,
+            inode: row.inode,
+            process: row.process_name.clone(),
+            command: owner
+                .filter(|info| !info.cmd.is_empty())
+                .map(|info| info.cmd.join(" ")),
+            exe: owner
+                .filter(|info| !info.exe.as_os_str().is_empty())
+                .map(|info| info.exe.display().to_string()),
+            first_seen: unix_seconds(row.first_seen),
+            recv_queue: row.queues.map(|queues| queues.recv),
+            send_queue: row.queues.map(|queues| queues.send),
+            rtt_ms: row.tcp_info.map(|info| f64::from(info.rtt_us) / 1000.0),
+            retransmits: row.tcp_info.map(|info| info.retransmits),
+            bytes_received: row.tcp_info.and_then(|info| info.bytes_received),
+            bytes_sent: row.tcp_info.and_then(|info| info.bytes_acked),
+            recv_rate: row.throughput.map(|throughput| throughput.recv),
+            send_rate: row.throughput.map(|throughput| throughput.send),
+        }
+    }
+}
+
+impl Record for SocketRecord {
+    const COLUMNS: &'static [&'static str] = &SOCKET_COLUMNS;
+
+    fn csv_cells(&self) -> Vec<String> {
+        vec![
+            self.taken_at.to_string(),
+            self.protocol.clone(),
+            self.family.clone(),
+            self.local_addr.clone(),
+            self.local_port.to_string(),
+            cell(&self.remote_addr),
+            cell(&self.remote_port),
+            cell(&self.state),
+            pids_cell(&self.pids),
+            cell(&self.uid),
+            self.inode.to_string(),
+            cell(&self.process),
+            cell(&self.command),
+            cell(&self.exe),
+            self.first_seen.to_string(),
+            cell(&self.recv_queue),
+            cell(&self.send_queue),
+            cell(&self.rtt_ms),
+            cell(&self.retransmits),
+            cell(&self.bytes_received),
+            cell(&self.bytes_sent),
+            cell(&self.recv_rate),
+            cell(&self.send_rate),
+        ]
+    }
+}
+
+/// A Unix socket flattened for export.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct UnixRecord {
+    pub taken_at: f64,
+    pub socket_type: String,
+    pub path: Option<String>,
+    pub state: String,
+    pub inode: u32,
+    pub peer_inode: Option<u32>,
+    pub pids: Vec<u32>,
+    pub process: Option<String>,
+    pub first_seen: f64,
+}
+
+impl UnixRecord {
+    pub fn new(row: &UnixSocketRow, taken_at: SystemTime) -> Self {
+        UnixRecord {
+            taken_at: unix_seconds(taken_at),
+            socket_type: row.socket_type.to_string(),
+            path: row.path.clone(),
+            state: row.state.to_string(),
+            inode: row.inode,
+            peer_inode: row.peer_inode,
+            pids: row.pids.clone(),
+            process: row.process_name.clone(),
+            first_seen: unix_seconds(row.first_seen),
+        }
+    }
+}
+
+impl Record for UnixRecord {
+    const COLUMNS: &'static [&'static str] = &[
+        "taken_at",
+        "socket_type",
+        "path",
+        "state",
+        "inode",
+        "peer_inode",
+        "pids",
+        "process",
+        "first_seen",
+    ];
+
+    fn csv_cells(&self) -> Vec<String> {
+        vec![
+            self.taken_at.to_string(),
+            self.socket_type.clone(),
+            cell(&self.path),
+            self.state.clone(),
+            self.inode.to_string(),
+            cell(&self.peer_inode),
+            pids_cell(&self.pids),
+            cell(&self.process),
+            self.first_seen.to_string(),
+        ]
+    }
+}
+
+/// A raw IP, packet or netlink socket flattened for export.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RawRecord {
+    pub taken_at: f64,
+    /// `RAW`, `PACKET` or `NETLINK`.
+    pub kind: String,
+    pub protocol: String,
+    /// Address, interface or netlink port, as the Raw pane shows it.
+    pub endpoint: String,
+    pub uid: Option<u32>,
+    pub inode: u32,
+    pub pids: Vec<u32>,
+    pub process: Option<String>,
+    pub first_seen: f64,
+}
+
+impl RawRecord {
+    pub fn new(row: &RawSocketRow, taken_at: SystemTime) -> Self {
+        RawRecord {
+            taken_at: unix_seconds(taken_at),
+            kind: row.kind.to_string(),
+            protocol: row.protocol_name(),
+            endpoint: row.endpoint.clone(),
+            uid: row.uid,
+            inode: row.inode,
+            pids: row.pids.clone(),
+            process: row.process_name.clone(),
+            first_seen: unix_seconds(row.first_seen),
+        }
+    }
+}
+
+impl Record for RawRecord {
+    const COLUMNS: &'static [&'static str] = &[
+        "taken_at",
+        "kind",
+        "protocol",
+        "endpoint",
+        "uid",
+        "inode",
+        "pids",
+        "process",
+        "first_seen",
+    ];
+
+    fn csv_cells(&self) -> Vec<String> {
+        vec![
+            self.taken_at.to_string(),
+            self.kind.clone(),
+            self.protocol.clone(),
+            self.endpoint.clone(),
+            cell(&self.uid),
+            self.inode.to_string(),
+            pids_cell(&self.pids),
+            cell(&self.process),
+            self.first_seen.to_string(),
+        ]
+    }
+}
+
+fn cell<T: ToString>(value: &Option<T>) -> String {
+    value.as_ref().map(T::to_string).unwrap_or_default()
+}
+
+fn pids_cell(pids: &[u32]) -> String {
+    let pids: Vec<String> = pids.iter().map(u32::to_string).collect();
+    pids.join(" ")
+}
+
+/// A socket opened, closed or changing state, as `poke watch` prints it.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct EventRecord {
+    pub at: f64,
+    /// `OPENED`, `CLOSED` or `STATE`.
+    pub event: String,
+    /// State before a state change.
+    pub previous_state: Option<String>,
+    /// Seconds a closed socket was open.
+    pub lived: Option<f64>,
+    pub socket: SocketRecord,
+}
+
+impl EventRecord {
+    pub fn new(event: &LifecycleEvent, processes: &HashMap<u32, ProcessInfo>) -> Self {
+        EventRecord {
+            at: unix_seconds(event.at),
+            event: event.change.to_string(),
+            previous_state: match event.change {
+                Change::StateChanged { from, .. } => Some(from.to_string()),
+                _ => None,
+            },
+            lived: event.lived().map(|lived| lived.as_secs_f64()),
+            socket: SocketRecord::new(&event.socket, processes, event.at),
+        }
+    }
+}
+
+pub fn write<W: Write, R: Record>(out: &mut W, format: Format, records: &[R]) -> io::Result<()> {
+    match format {
+        Format::Json => {
+            serde_json::to_writer_pretty(&mut *out, records)?;
+            writeln!(out)
+        }
+        Format::Ndjson => {
+            for record in records {
+                serde_json::to_writer(&mut *out, record)?;
+                writeln!(out)?;
+            }
+            Ok(())
+        }
+        Format::Csv => {
+            write_csv_line(out, R::COLUMNS.iter())?;
+            for record in records {
+                write_csv_line(out, record.csv_cells().iter())?;
+            }
+            Ok(())
+        }
+    }
+}
+
+/// RFC 4180: cells with separators, quotes or line breaks are quoted.
+fn write_csv_line<W: Write, S: AsRef<str>>(
+    out: &mut W,
+    cells: impl Iterator<Item = S>,
+) -> io::Result<()> {
+    let cells: Vec<String> = cells
+        .map(|cell| {
+            let cell = cell.as_ref();
+            if cell.contains(&[',', '"', '\n', '\r'][..]) {
+                format!("\"{}\"", cell.replace('"', "\"\""))
+            } else {
+                cell.to_owned()
+            }
+        })
+        .collect();
+    write!(out, "{}\r\n", cells.join(","))
+}
+
+/// Writes the records to a new file in the working directory named after
+/// `stem`, with a `-2`, `-3`... suffix when the name is taken; returns the
+/// name.
+pub fn write_new_file<R: Record>(
+    stem: &str,
+    format: Format,
+    records: &[R],
+) -> Result<String, ConnectionToolsError> {
+    let export_error = |path: &str, err: io::Error| ConnectionToolsError::Export {
+        message: format!("{}: {}", path, err),
+    };
+    let mut number = 1;
+    let (path, file) = loop {
+        let path = match number {
+            1 => format!("{}.{}", stem, format),
+            _ => format!("{}-{}.{}", stem, number, format),
+        };
+        match OpenOptions::new().write(true).create_new(true).open(&path) {
+            Ok(file) => break (path, file),
+            Err(ref err) if err.kind() == io::ErrorKind::AlreadyExists => number += 1,
+            Err(err) => return Err(export_error(&path, err)),
+        }
+    };
+    let mut out = BufWriter::new(file);
+    write(&mut out, format, records)
+        .and_then(|_| out.flush())
+        .map_err(|err| export_error(&path, err))?;
+    Ok(path)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{write, write_new_file, Format, Record, SocketRecord, SOCKET_COLUMNS};
+    use crate::sockets::{SocketRow, TcpInfo};
+    use netstat2::TcpState;
+    use std::collections::HashMap;
+    use std::fs;
+    use std::time::{Duration, UNIX_EPOCH};
+
+    fn record() -> SocketRecord {
+        let taken_at = UNIX_EPOCH + Duration::from_secs(1_600_000_000);
+        let row = SocketRow {
+            pids: vec![7, 9],
+            inode: 1,
+            uid: Some(1000),
+            process_name: Some("web, \"main\"".to_owned()),
+            first_seen: taken_at,
+            tcp_info: Some(TcpInfo {
+                rtt_us: 1500,
+                ..TcpInfo::default()
+            }),
+            ..SocketRow::test_tcp("127.0.0.1:8080", "10.0.0.1:443", TcpState::Established)
+        };
+        SocketRecord::new(&row, &HashMap::new(), taken_at)
+    }
+
+    fn export(format: Format) -> String {
+        let mut out = Vec::new();
+        write(&mut out, format, &[record(), record()]).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn json_and_ndjson() {
+        let json: serde_json::Value = serde_json::from_str(&export(Format::Json)).unwrap();
+        assert_eq!(json.as_array().unwrap().len(), 2);
+        assert_eq!(json[0]["state"], "ESTABLISHED");
+        assert_eq!(json[0]["pids"], serde_json::json!([7, 9]));
+        assert_eq!(json[0]["rtt_ms"], 1.5);
+        assert!(json[0]["recv_queue"].is_null());
+
+        let ndjson = export(Format::Ndjson);
+        let lines: Vec<&str> = ndjson.lines().collect();
+        assert_eq!(lines.len(), 2);
+        let line: serde_json::Value = serde_json::from_str(lines[0]).unwrap();
+        assert_eq!(line, json[0]);
+    }
+
+    #[test]
+    fn csv_quotes_cells() {
+        assert_eq!(record().csv_cells().len(), SOCKET_COLUMNS.len());
+
+        let csv = export(Format::Csv);
+        let lines: Vec<&str> = csv.split("\r\n").collect();
+        assert_eq!(lines.len(), 4);
+        assert!(lines[0].starts_with("taken_at,protocol,family,"));
+        assert!(lines[1].starts_with("1600000000,TCP,IPv4,127.0.0.1,8080,10.0.0.1,443,"));
+        assert!(lines[1].contains(",7 9,1000,1,\"web, \"\"main\"\"\",,,"));
+        assert_eq!(lines[3], "");
+    }
+
+    #[test]
+    fn new_files_never_overwrite() {
+        let stem = std::env::temp_dir().join(format!("poke-test-{}", std::process::id()));
+        let stem = stem.to_str().unwrap();
+        let first = write_new_file(stem, Format::Csv, &[record()]).unwrap();
+        let second = write_new_file(stem, Format::Csv, &[record()]).unwrap();
+        assert_eq!(first, format!("{}.csv", stem));
+        assert_eq!(second, format!("{}-2.csv", stem));
+        assert_eq!(fs::read_to_string(&first).unwrap().lines().count(), 2);
+        fs::remove_file(first).unwrap();
+        fs::remove_file(second).unwrap();
+    }
+}
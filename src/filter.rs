@@ -0,0 +1,397 @@
+//! Query language for the socket panes.
+//!
+//! A query is a whitespace separated list of terms and a socket has to match
+//! all of them. Every term can be negated with a leading `!`.
+//!
+//! * `port:N` - local or remote port, `lport:N` / `rport:N` for one side only
+//! * `state:NAME` - TCP state prefix, case and `_` insensitive (`state:est`)
+//! * `pid:N` - owning process id
+//! * `proc:NAME` - owning process name substring
+//! * `local:ADDR`, `remote:ADDR` - address or CIDR network (`10.0.0.0/8`)
+//! * `lo` - sockets bound to a loopback address
+//! * anything else - substring of any of the displayed columns
+//!
+//! Unix sockets only have a state, owners and a path, so the port and
+//! address terms never match them. Raw, packet and netlink sockets only
+//! match `pid`, `proc` and free text.
+
+use std::net::IpAddr;
+
+use crate::errors::ConnectionToolsError;
+use crate::format::format_endpoint;
+use crate::sockets::{RawSocketRow, SocketRow, UnixSocketRow};
+
+#[derive(Debug, Clone, PartialEq)]
+struct Network {
+    addr: IpAddr,
+    prefix: u8,
+}
+
+impl Network {
+    fn parse(value: &str) -> Result<Self, ConnectionToolsError> {
+        let mut parts = value.splitn(2, '/');
+        let addr: IpAddr = parts
+            .next()
+            .unwrap_or_default()
+            .parse()
+            .map_err(|_| invalid_filter(format!("'{}' is not an ip address", value)))?;
+        let max_prefix = match addr {
+            IpAddr::V4(_) => 32,
+            IpAddr::V6(_) => 128,
+        };
+        let prefix = match parts.next() {
+            None => max_prefix,
+            Some(prefix) => prefix
+                .parse::<u8>()
+                .ok()
+                .filter(|prefix| *prefix <= max_prefix)
+                .ok_or_else(|| invalid_filter(format!("'{}' is not a valid prefix", prefix)))?,
+        };
+        Ok(Network { addr, prefix })
+    }
+
+    fn contains(&self, ip: &IpAddr) -> bool {
+        let ip = match ip {
+            IpAddr::V6(v6) => v6.to_ipv4_mapped().map(IpAddr::V4).unwrap_or(*ip),
+            IpAddr::V4(_) => *ip,
+        };
+        match (self.addr, ip) {
+            (IpAddr::V4(net), IpAddr::V4(ip)) => {
+                prefix_matches(&net.octets(), &ip.octets(), self.prefix)
+            }
+            (IpAddr::V6(net), IpAddr::V6(ip)) => {
+                prefix_matches(&net.octets(), &ip.octets(), self.prefix)
+            }
+            _ => false,
+        }
+    }
+}
+
+fn prefix_matches(net: &[u8], ip: &[u8], prefix: u8) -> bool {
+    let full_bytes = (prefix / 8) as usize;
+    let rest_bits = prefix % 8;
+    if net[..full_bytes] != ip[..full_bytes] {
+        return false;
+    }
+    if rest_bits == 0 {
+        return true;
+    }
+    let mask = 0xffu8 << (8 - rest_bits);
+    net[full_bytes] & mask == ip[full_bytes] & mask
+}
+
+/// Keys of the `key:value` terms.
+const KEYS: [&str; 8] = [
+    "port", "lport", "rport", "state", "pid", "proc", "local", "remote",
+];
+
+#[derive(Debug, Clone, PartialEq)]
+enum Predicate {
+    Port(u16),
+    LocalPort(u16),
+    RemotePort(u16),
+    State(String),
+    Pid(u32),
+    Process(String),
+    Local(Network),
+    Remote(Network),
+    Loopback,
+    Text(String),
+}
+
+impl Predicate {
+    fn parse(term: &str) -> Result<Self, ConnectionToolsError> {
+        // only known keys make a key:value term, so that addresses like
+        // `::1` or `[::1]:443` are searched as text
+        let (name, value) = match term.split_once(':') {
+            Some((name, value)) if KEYS.contains(&name) => (name, value),
+            _ if term == "lo" => return Ok(Predicate::Loopback),
+            _ => return Ok(Predicate::Text(term.to_lowercase())),
+        };
+        if value.is_empty() {
+            return Err(invalid_filter(format!("'{}' has no value", name)));
+        }
+
+        match name {
+            "port" => parse_number(value).map(Predicate::Port),
+            "lport" => parse_number(value).map(Predicate::LocalPort),
+            "rport" => parse_number(value).map(Predicate::RemotePort),
+            "state" => Ok(Predicate::State(normalize_state(value))),
+            "pid" => parse_number(value).map(Predicate::Pid),
+            "proc" => Ok(Predicate::Process(value.to_lowercase())),
+            "local" => Network::parse(value).map(Predicate::Local),
+            "remote" => Network::parse(value).map(Predicate::Remote),
+            _ => unreachable!("'{}' is in KEYS", name),
+        }
+    }
+
+    fn matches(&self, row: &SocketRow) -> bool {
+        match self {
+            Predicate::Port(port) => row.local_port == *port || row.remote_port == Some(*port),
+            Predicate::LocalPort(port) => row.local_port == *port,
+            Predicate::RemotePort(port) => row.remote_port == Some(*port),
+            Predicate::State(state) => row
+                .state
+                .map(|row_state| normalize_state(&row_state.to_string()).starts_with(state))
+                .unwrap_or(false),
+            Predicate::Pid(pid) => row.pids.contains(pid),
+            Predicate::Process(name) => row
+                .process_name
+                .as_ref()
+                .map(|process_name| process_name.to_lowercase().contains(name))
+                .unwrap_or(false),
+            Predicate::Local(network) => network.contains(&row.local_addr),
+            Predicate::Remote(network) => row
+                .remote_addr
+                .map(|remote_addr| network.contains(&remote_addr))
+                .unwrap_or(false),
+            Predicate::Loopback => row.local_addr.is_loopback(),
+            Predicate::Text(text) => searchable_columns(row)
+                .iter()
+                .any(|column| column.to_lowercase().contains(text)),
+        }
+    }
+
+    fn matches_unix(&self, row: &UnixSocketRow) -> bool {
+        match self {
+            Predicate::State(state) => normalize_state(&row.state.to_string()).starts_with(state),
+            Predicate::Pid(pid) => row.pids.contains(pid),
+            Predicate::Process(name) => row
+                .process_name
+                .as_ref()
+                .map(|process_name| process_name.to_lowercase().contains(name))
+                .unwrap_or(false),
+            Predicate::Text(text) => unix_searchable_columns(row)
+                .iter()
+                .any(|column| column.to_lowercase().contains(text)),
+            Predicate::Port(_)
+            | Predicate::LocalPort(_)
+            | Predicate::RemotePort(_)
+            | Predicate::Local(_)
+            | Predicate::Remote(_)
+            | Predicate::Loopback => false,
+        }
+    }
+
+    fn matches_raw(&self, row: &RawSocketRow) -> bool {
+        match self {
+            Predicate::Pid(pid) => row.pids.contains(pid),
+            Predicate::Process(name) => row
+                .process_name
+                .as_ref()
+                .map(|process_name| process_name.to_lowercase().contains(name))
+                .unwrap_or(false),
+            Predicate::Text(text) => raw_searchable_columns(row)
+                .iter()
+                .any(|column| column.to_lowercase().contains(text)),
+            _ => false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Term {
+    negated: bool,
+    predicate: Predicate,
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Filter {
+    terms: Vec<Term>,
+}
+
+impl Filter {
+    pub fn parse(query: &str) -> Result<Self, ConnectionToolsError> {
+        let terms = query
+            .split_whitespace()
+            .map(|term| {
+                let (negated, term) = match term.strip_prefix('!') {
+                    Some(term) => (true, term),
+                    None => (false, term),
+                };
+                if term.is_empty() {
+                    return Err(invalid_filter("'!' without a term".to_owned()));
+                }
+                Predicate::parse(term).map(|predicate| Term { negated, predicate })
+            })
+            .collect::<Result<Vec<Term>, ConnectionToolsError>>()?;
+
+        Ok(Filter { terms })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.terms.is_empty()
+    }
+
+    pub fn matches(&self, row: &SocketRow) -> bool {
+        self.terms
+            .iter()
+            .all(|term| term.predicate.matches(row) != term.negated)
+    }
+
+    pub fn matches_unix(&self, row: &UnixSocketRow) -> bool {
+        self.terms
+            .iter()
+            .all(|term| term.predicate.matches_unix(row) != term.negated)
+    }
+
+    pub fn matches_raw(&self, row: &RawSocketRow) -> bool {
+        self.terms
+            .iter()
+            .all(|term| term.predicate.matches_raw(row) != term.negated)
+    }
+}
+
+fn searchable_columns(row: &SocketRow) -> Vec<String> {
+    let mut columns = vec![format_endpoint(row.local_addr, row.local_port)];
+    if let (Some(remote_addr), Some(remote_port)) = (row.remote_addr, row.remote_port) {
+        columns.push(format_endpoint(remote_addr, remote_port));
+    }
+    if let Some(state) = row.state {
+        columns.push(state.to_string());
+    }
+    if let Some(process_name) = &row.process_name {
+        columns.push(process_name.clone());
+    }
+    columns.extend(row.pids.iter().map(|pid| pid.to_string()));
+    columns
+}
+
+fn unix_searchable_columns(row: &UnixSocketRow) -> Vec<String> {
+    let mut columns = vec![
+        row.socket_type.to_string(),
+        row.state.to_string(),
+        row.inode.to_string(),
+    ];
+    if let Some(path) = &row.path {
+        columns.push(path.clone());
+    }
+    if let Some(process_name) = &row.process_name {
+        columns.push(process_name.clone());
+    }
+    columns.extend(row.pids.iter().map(|pid| pid.to_string()));
+    columns
+}
+
+fn raw_searchable_columns(row: &RawSocketRow) -> Vec<String> {
+    let mut columns = vec![
+        row.kind.to_string(),
+        row.protocol_name(),
+        row.endpoint.clone(),
+        row.inode.to_string(),
+    ];
+    if let Some(process_name) = &row.process_name {
+        columns.push(process_name.clone());
+    }
+    columns.extend(row.pids.iter().map(|pid| pid.to_string()));
+    columns
+}
+
+fn normalize_state(state: &str) -> String {
+    state
+        .chars()
+        .filter(|c| *c != '_' && *c != '-')
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+fn parse_number<T: std::str::FromStr>(value: &str) -> Result<T, ConnectionToolsError> {
+    value
+        .parse()
+        .map_err(|_| invalid_filter(format!("'{}' is not a number", value)))
+}
+
+fn invalid_filter(message: String) -> ConnectionToolsError {
+    ConnectionToolsError::InvalidFilter { message }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Filter;
+    use crate::sockets::{SocketRow, UnixSocketRow, UnixState, UnixType};
+    use netstat2::TcpState;
+    use std::net::SocketAddr;
+    use std::time::SystemTime;
+
+    fn row(local_addr: &str, local_port: u16, remote_addr: &str, state: TcpState) -> SocketRow {
+        let local = SocketAddr::new(local_addr.parse().unwrap(), local_port);
+        let remote = SocketAddr::new(remote_addr.parse().unwrap(), 40000);
+        SocketRow {
+            pids: vec![1234],
+            inode: 1,
+            process_name: Some("postgres".to_owned()),
+            ..SocketRow::test_tcp(&local.to_string(), &remote.to_string(), state)
+        }
+    }
+
+    fn matches(query: &str, row: &SocketRow) -> bool {
+        Filter::parse(query).unwrap().matches(row)
+    }
+
+    #[test]
+    fn all_terms_must_match() {
+        let db = row("10.1.2.3", 5432, "10.9.8.7", TcpState::Established);
+
+        assert!(matches("", &db));
+        assert!(matches(
+            "port:5432 state:established pid:1234 proc:postgres remote:10.0.0.0/8 !lo",
+            &db
+        ));
+        assert!(matches("state:est proc:POST", &db));
+        assert!(!matches("port:5432 pid:1", &db));
+        assert!(!matches("remote:192.168.0.0/16", &db));
+    }
+
+    #[test]
+    fn loopback_and_free_text() {
+        let local = row("127.0.0.1", 8080, "127.0.0.1", TcpState::CloseWait);
+
+        assert!(matches("lo", &local));
+        assert!(!matches("!lo", &local));
+        assert!(matches("state:close_wait", &local));
+        assert!(matches("wait 808", &local));
+        assert!(matches("remote:127.0.0.1", &local));
+    }
+
+    #[test]
+    fn ipv6_free_text() {
+        let local = row("::1", 443, "::1", TcpState::Established);
+
+        assert!(matches("::1", &local));
+        assert!(matches("[::1]:443", &local));
+        assert!(!matches("fe80::1", &local));
+        // an unknown key is just text too
+        assert!(!matches("color:red", &local));
+    }
+
+    #[test]
+    fn invalid_queries() {
+        assert!(Filter::parse("port:http").is_err());
+        assert!(Filter::parse("remote:10.0.0.0/33").is_err());
+        assert!(Filter::parse("pid:").is_err());
+        assert!(Filter::parse("state:").is_err());
+        assert!(Filter::parse("!").is_err());
+    }
+
+    #[test]
+    fn unix_sockets() {
+        let docker = UnixSocketRow {
+            socket_type: UnixType::Stream,
+            path: Some("/run/docker.sock".to_owned()),
+            state: UnixState::Listen,
+            inode: 4242,
+            peer_inode: None,
+            pids: vec![800],
+            process_name: Some("dockerd".to_owned()),
+            first_seen: SystemTime::now(),
+        };
+        let matches_unix = |query: &str| Filter::parse(query).unwrap().matches_unix(&docker);
+
+        assert!(matches_unix(
+            "docker.sock state:listen pid:800 proc:dockerd"
+        ));
+        assert!(matches_unix("4242"));
+        assert!(!matches_unix("port:80"));
+        assert!(matches_unix("!lo"));
+    }
+}
@@ -0,0 +1,96 @@
+//! Values as poke shows them in the TUI, on the command line and in exports.
+
+use std::fmt::Display;
+use std::net::{IpAddr, SocketAddr};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::sockets::Throughput;
+
+/// `addr:port`, with brackets around IPv6 addresses.
+pub fn format_endpoint(addr: IpAddr, port: u16) -> String {
+    SocketAddr::new(addr, port).to_string()
+}
+
+pub fn join_pids(pids: &[u32]) -> String {
+    pids.iter()
+        .map(|pid| pid.to_string())
+        .collect::<Vec<String>>()
+        .join(",")
+}
+
+pub fn display_or_star<T: Display>(value: &Option<T>) -> String {
+    value
+        .as_ref()
+        .map(|v| v.to_string())
+        .unwrap_or_else(|| "*".to_owned())
+}
+
+/// Local wall clock time as `HH:MM:SS`.
+pub fn format_clock(time: SystemTime) -> String {
+    let seconds = time
+        .duration_since(UNIX_EPOCH)
+        .map(|since_epoch| since_epoch.as_secs() as libc::time_t)
+        .unwrap_or_default();
+    // SAFETY: tm is plain data that localtime_r fills in
+    let mut tm: libc::tm = unsafe { std::mem::zeroed() };
+    // SAFETY: both pointers are valid for the duration of the call
+    unsafe { libc::localtime_r(&seconds, &mut tm) };
+    format!("{:02}:{:02}:{:02}", tm.tm_hour, tm.tm_min, tm.tm_sec)
+}
+
+pub fn format_throughput(throughput: Throughput) -> String {
+    format!(
+        "in {}/s, out {}/s",
+        format_bytes(throughput.recv),
+        format_bytes(throughput.send)
+    )
+}
+
+/// Durations in their two largest units, e.g. `5m03s` or `2h15m`.
+pub fn format_duration(duration: Duration) -> String {
+    let seconds = duration.as_secs();
+    match seconds {
+        0..=59 => format!("{}s", seconds),
+        60..=3599 => format!("{}m{:02}s", seconds / 60, seconds % 60),
+        3600..=86399 => format!("{}h{:02}m", seconds / 3600, seconds % 3600 / 60),
+        _ => format!("{}d{:02}h", seconds / 86400, seconds % 86400 / 3600),
+    }
+}
+
+/// Byte counts with a binary unit, e.g. `1.5 MiB`.
+pub fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KiB", "MiB", "GiB", "TiB"];
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit + 1 < UNITS.len() {
+        value /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} B", bytes)
+    } else {
+        format!("{:.1} {}", value, UNITS[unit])
+    }
+}
+
+/// Seconds since the Unix epoch, with a fraction.
+pub fn unix_seconds(time: SystemTime) -> f64 {
+    time.duration_since(UNIX_EPOCH)
+        .map(|since_epoch| since_epoch.as_secs_f64())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod test {
+    use super::{format_bytes, format_duration, format_endpoint};
+    use std::time::Duration;
+
+    #[test]
+    fn human_readable_values() {
+        assert_eq!(format_endpoint("::1".parse().unwrap(), 80), "[::1]:80");
+        assert_eq!(format_duration(Duration::from_secs(303)), "5m03s");
+        assert_eq!(format_duration(Duration::from_secs(90_000)), "1d01h");
+        assert_eq!(format_bytes(1023), "1023 B");
+        assert_eq!(format_bytes(1536 * 1024), "1.5 MiB");
+    }
+}
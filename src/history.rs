@@ -0,0 +1,191 @@
+//! Past live snapshots, kept to go back to while live updates are paused.
+
+use std::collections::VecDeque;
+use std::mem;
+
+use crate::collector::Snapshot;
+use crate::processes::ProcessInfo;
+use crate::sockets::{RawSocketRow, SocketRow, UnixSocketRow};
+
+/// Snapshots kept at most, whatever the refresh interval.
+pub const HISTORY_LEN: usize = 1200;
+
+/// Memory the kept snapshots may take, as estimated by `estimated_bytes`.
+pub const HISTORY_BYTES: usize = 128 * 1024 * 1024;
+
+/// Oldest snapshot first. Only the last snapshot keeps the command lines and
+/// environments of its processes, which are large and rarely needed to look
+/// back.
+pub struct History {
+    snapshots: VecDeque<(Snapshot, usize)>,
+    capacity: usize,
+    budget: usize,
+    bytes: usize,
+}
+
+impl History {
+    pub fn new(capacity: usize, budget: usize) -> Self {
+        History {
+            snapshots: VecDeque::new(),
+            capacity,
+            budget,
+            bytes: 0,
+        }
+    }
+
+    /// Keeps a copy of `snapshot`, dropping the oldest snapshots past the
+    /// capacity or the budget; returns how many were dropped. The last
+    /// snapshot is always kept.
+    pub fn push(&mut self, snapshot: &Snapshot) -> usize {
+        if let Some((previous, bytes)) = self.snapshots.back_mut() {
+            for info in previous.processes.values_mut() {
+                info.cmd = Vec::new();
+                info.environ = Vec::new();
+            }
+            self.bytes -= *bytes;
+            *bytes = estimated_bytes(previous);
+            self.bytes += *bytes;
+        }
+        let bytes = estimated_bytes(snapshot);
+        self.snapshots.push_back((snapshot.clone(), bytes));
+        self.bytes += bytes;
+
+        let mut dropped = 0;
+        while self.snapshots.len() > 1
+            && (self.snapshots.len() > self.capacity || self.bytes > self.budget)
+        {
+            if let Some((_, bytes)) = self.snapshots.pop_front() {
+                self.bytes -= bytes;
+                dropped += 1;
+            }
+        }
+        dropped
+    }
+
+    pub fn get(&self, index: usize) -> Option<&Snapshot> {
+        self.snapshots.get(index).map(|(snapshot, _)| snapshot)
+    }
+
+    pub fn last(&self) -> Option<&Snapshot> {
+        self.snapshots.back().map(|(snapshot, _)| snapshot)
+    }
+
+    pub fn len(&self) -> usize {
+        self.snapshots.len()
+    }
+
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = &Snapshot> + ExactSizeIterator {
+        self.snapshots.iter().map(|(snapshot, _)| snapshot)
+    }
+}
+
+/// The rows, processes and events with the strings they own, leaving out
+/// the bookkeeping of the collections.
+fn estimated_bytes(snapshot: &Snapshot) -> usize {
+    let owned = |name: &Option<String>, pids: &[u32]| {
+        name.as_ref().map_or(0, String::len) + mem::size_of_val(pids)
+    };
+    let mut bytes = mem::size_of::<Snapshot>();
+    if let Ok(sockets) = &snapshot.sockets {
+        for row in sockets.rows() {
+            bytes += mem::size_of::<SocketRow>() + owned(&row.process_name, &row.pids);
+        }
+        for row in &sockets.unix_sockets {
+            bytes += mem::size_of::<UnixSocketRow>()
+                + owned(&row.process_name, &row.pids)
+                + row.path.as_ref().map_or(0, String::len);
+        }
+        for row in &sockets.raw_sockets {
+            bytes += mem::size_of::<RawSocketRow>()
+                + owned(&row.process_name, &row.pids)
+                + row.endpoint.len();
+        }
+    }
+    for info in snapshot.processes.values() {
+        bytes += mem::size_of::<(u32, ProcessInfo)>()
+            + info.name.len()
+            + info.status.len()
+            + info.exe.as_os_str().len()
+            + info
+                .cmd
+                .iter()
+                .chain(&info.environ)
+                .map(String::len)
+                .sum::<usize>();
+    }
+    for event in &snapshot.events {
+        bytes += mem::size_of_val(event) + owned(&event.socket.process_name, &event.socket.pids);
+    }
+    bytes
+}
+
+#[cfg(test)]
+mod test {
+    use super::History;
+    use crate::collector::Snapshot;
+    use crate::processes::{ProcessInfo, ProcessKey};
+    use crate::sockets::{SocketRow, SocketsContainer};
+    use netstat2::TcpState;
+    use std::collections::HashMap;
+    use std::time::{Duration, SystemTime};
+
+    fn snapshot(sockets: u16) -> Snapshot {
+        let info = ProcessInfo {
+            key: ProcessKey {
+                pid: 7,
+                start_ticks: 1,
+            },
+            name: "web".to_owned(),
+            status: "Run".to_owned(),
+            cmd: vec!["web".to_owned(), "--port".to_owned()],
+            exe: "/usr/bin/web".into(),
+            environ: vec!["TOKEN=secret".to_owned()],
+            memory: 0,
+            virtual_memory: 0,
+            start_time: 0,
+            cpu_usage: 0.0,
+        };
+        let rows = (0..sockets)
+            .map(|port| {
+                let local = format!("127.0.0.1:{}", port);
+                SocketRow::test_tcp(&local, "10.0.0.1:443", TcpState::Established)
+            })
+            .collect();
+        Snapshot {
+            sockets: Ok(SocketsContainer::from_rows(rows)),
+            processes: vec![(7, info)].into_iter().collect::<HashMap<_, _>>(),
+            taken_at: SystemTime::now(),
+            duration: Duration::from_millis(1),
+            events: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn kept_within_capacity_and_budget() {
+        let mut history = History::new(3, usize::MAX);
+        let dropped: Vec<usize> = (0..5).map(|_| history.push(&snapshot(1))).collect();
+        assert_eq!(dropped, vec![0, 0, 0, 1, 1]);
+        assert_eq!(history.len(), 3);
+
+        let one = super::estimated_bytes(history.get(0).unwrap());
+        let mut history = History::new(100, one * 3);
+        history.push(&snapshot(1));
+        history.push(&snapshot(1));
+        assert_eq!(history.push(&snapshot(1)), 1);
+        // a snapshot over the whole budget still shows
+        assert_eq!(history.push(&snapshot(1000)), 2);
+        assert_eq!(history.len(), 1);
+    }
+
+    #[test]
+    fn only_the_last_commands_are_kept() {
+        let mut history = History::new(3, usize::MAX);
+        history.push(&snapshot(1));
+        history.push(&snapshot(1));
+        let past = &history.get(0).unwrap().processes[&7];
+        assert_eq!(past.name, "web");
+        assert!(past.cmd.is_empty() && past.environ.is_empty());
+        let last = &history.last().unwrap().processes[&7];
+        assert_eq!(last.environ, vec!["TOKEN=secret"]);
+    }
+}
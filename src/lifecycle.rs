@@ -0,0 +1,384 @@
+//! Sockets opened, closed or changing state between two snapshots.
+
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::mem;
+use std::time::{Duration, SystemTime};
+
+use netstat2::TcpState;
+use serde::{Deserialize, Serialize};
+
+use crate::sockets::{tcp_state, SocketKey, SocketRow, SocketsContainer};
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum Change {
+    Opened,
+    Closed,
+    StateChanged {
+        #[serde(with = "tcp_state")]
+        from: TcpState,
+        #[serde(with = "tcp_state")]
+        to: TcpState,
+    },
+}
+
+impl fmt::Display for Change {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Change::Opened => write!(f, "OPENED"),
+            Change::Closed => write!(f, "CLOSED"),
+            Change::StateChanged { .. } => write!(f, "STATE"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LifecycleEvent {
+    pub at: SystemTime,
+    pub change: Change,
+    /// The socket after the change; a closed socket as it was last seen.
+    pub socket: SocketRow,
+}
+
+impl LifecycleEvent {
+    /// How long a closed socket was open, as far as the snapshots can tell.
+    pub fn lived(&self) -> Option<Duration> {
+        match self.change {
+            Change::Closed => self.at.duration_since(self.socket.first_seen).ok(),
+            _ => None,
+        }
+    }
+}
+
+/// Remembers the TCP and UDP sockets of the last snapshot.
+pub struct Tracker {
+    previous: Option<HashMap<SocketKey, SocketRow>>,
+    /// Keys of `previous` by address, as keys without inode.
+    previous_by_address: HashMap<SocketKey, SocketKey>,
+    /// Addresses of the sockets the kernel reported destroyed since the last
+    /// snapshot, as keys without inode.
+    destroyed: HashSet<SocketKey>,
+    /// Addresses destroyed before the last snapshot. A socket destroyed
+    /// while a snapshot was being taken may still be in it, or in the next
+    /// one, and is not reported opened again.
+    recently_destroyed: HashSet<SocketKey>,
+    /// TIME_WAIT remains of destroyed connections, already reported closed.
+    lingering: HashSet<SocketKey>,
+}
+
+impl Tracker {
+    pub fn new() -> Self {
+        Tracker {
+            previous: None,
+            previous_by_address: HashMap::new(),
+            destroyed: HashSet::new(),
+            recently_destroyed: HashSet::new(),
+            lingering: HashSet::new(),
+        }
+    }
+
+    /// A socket the kernel destroyed, seen in the last snapshot or not. It is
+    /// reported closed with its final counters, and left out of the next diff.
+    pub fn destroyed(&mut self, mut socket: SocketRow, at: SystemTime) -> LifecycleEvent {
+        let address = without_inode(&socket.key());
+        let key = self.previous_by_address.remove(&address);
+        let before = match (self.previous.as_mut(), key) {
+            (Some(previous), Some(key)) => previous.remove(&key),
+            _ => None,
+        };
+        // the kernel no longer knows the owner once the socket is gone
+        if let Some(before) = before {
+            socket.inode = before.inode;
+            socket.pids = before.pids;
+            socket.process_name = before.process_name;
+            socket.first_seen = before.first_seen;
+        }
+        self.destroyed.insert(address);
+        LifecycleEvent {
+            at,
+            change: Change::Closed,
+            socket,
+        }
+    }
+
+    /// The first snapshot is only a baseline and reports nothing. Closed
+    /// sockets come first, then state changes and opened sockets in the
+    /// order of `current`.
+    pub fn diff(&mut self, current: &SocketsContainer, at: SystemTime) -> Vec<LifecycleEvent> {
+        let mut destroyed = mem::take(&mut self.destroyed);
+        let recently_destroyed = mem::replace(&mut self.recently_destroyed, destroyed.clone());
+        destroyed.extend(recently_destroyed);
+        let lingering: HashSet<SocketKey> = current
+            .rows()
+            .filter(|row| {
+                matches!(
+                    row.state,
+                    Some(TcpState::TimeWait) | Some(TcpState::FinWait2)
+                )
+            })
+            .map(|row| without_inode(&row.key()))
+            .filter(|address| destroyed.contains(address) || self.lingering.contains(address))
+            .collect();
+        self.lingering = lingering;
+        let lingering = &self.lingering;
+        let tracked: Vec<&SocketRow> = current
+            .rows()
+            .filter(|row| {
+                let address = without_inode(&row.key());
+                !lingering.contains(&address) && !destroyed.contains(&address)
+            })
+            .collect();
+
+        let current_rows: HashMap<SocketKey, SocketRow> = tracked
+            .iter()
+            .map(|row| (row.key(), (*row).clone()))
+            .collect();
+        self.previous_by_address = current_rows
+            .keys()
+            .map(|key| (without_inode(key), key.clone()))
+            .collect();
+        let previous = match self.previous.replace(current_rows) {
+            Some(previous) => previous,
+            None => return Vec::new(),
+        };
+        let current_rows = self.previous.as_ref().expect("just replaced");
+
+        // a connection moving to TIME_WAIT loses its inode, so such a row is
+        // looked up again by its addresses; any other socket with a new inode
+        // is a new socket, e.g. a restarted listener or a reused 4-tuple
+        let mut unmatched: HashMap<SocketKey, &SocketKey> = previous
+            .keys()
+            .filter(|key| !current_rows.contains_key(key))
+            .map(|key| (without_inode(key), key))
+            .collect();
+        let mut moved = HashSet::new();
+        let mut changes = Vec::new();
+        for row in tracked {
+            let key = row.key();
+            let before = match previous.get(&key) {
+                Some(before) => Some(before.state),
+                None if row.state == Some(TcpState::TimeWait) => {
+                    unmatched.remove(&without_inode(&key)).map(|key| {
+                        moved.insert(key);
+                        previous[key].state
+                    })
+                }
+                None => None,
+            };
+            let change = match before {
+                None => Change::Opened,
+                Some(before) => match (before, row.state) {
+                    (Some(from), Some(to)) if from != to => Change::StateChanged { from, to },
+                    _ => continue,
+                },
+            };
+            changes.push(LifecycleEvent {
+                at,
+                change,
+                socket: row.clone(),
+            });
+        }
+
+        let mut closed: Vec<&SocketRow> = previous
+            .iter()
+            .filter(|(key, _)| !current_rows.contains_key(key) && !moved.contains(key))
+            .map(|(_, row)| row)
+            .collect();
+        closed.sort_by_key(|row| (row.local_port, row.inode));
+        let mut events: Vec<LifecycleEvent> = closed
+            .into_iter()
+            .map(|row| LifecycleEvent {
+                at,
+                change: Change::Closed,
+                socket: row.clone(),
+            })
+            .collect();
+        events.append(&mut changes);
+        events
+    }
+}
+
+/// A connection moving to TIME_WAIT loses its inode, so connections are
+/// followed by their addresses.
+pub fn without_inode(key: &SocketKey) -> SocketKey {
+    SocketKey {
+        inode: 0,
+        ..key.clone()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Change, Tracker};
+    use crate::sockets::{SocketRow, SocketsContainer};
+    use netstat2::TcpState;
+    use std::time::{Duration, SystemTime};
+
+    fn row(local_port: u16, state: TcpState, first_seen: SystemTime) -> SocketRow {
+        let local = format!("127.0.0.1:{}", local_port);
+        SocketRow {
+            first_seen,
+            ..SocketRow::test_tcp(&local, "10.0.0.1:443", state)
+        }
+    }
+
+    #[test]
+    fn opened_closed_and_state_changes() {
+        let start = SystemTime::now();
+        let later = start + Duration::from_secs(3);
+        let mut tracker = Tracker::new();
+
+        let baseline = SocketsContainer::from_rows(vec![
+            row(1, TcpState::SynSent, start),
+            row(2, TcpState::Established, start),
+        ]);
+        assert!(tracker.diff(&baseline, start).is_empty());
+
+        let current = SocketsContainer::from_rows(vec![
+            row(1, TcpState::Established, start),
+            row(3, TcpState::SynSent, later),
+        ]);
+        let events = tracker.diff(&current, later);
+
+        let changes: Vec<(Change, u16)> = events
+            .iter()
+            .map(|event| (event.change, event.socket.local_port))
+            .collect();
+        assert_eq!(
+            changes,
+            vec![
+                (Change::Closed, 2),
+                (
+                    Change::StateChanged {
+                        from: TcpState::SynSent,
+                        to: TcpState::Established
+                    },
+                    1
+                ),
+                (Change::Opened, 3),
+            ]
+        );
+        assert_eq!(events[0].lived(), Some(Duration::from_secs(3)));
+        assert_eq!(events[2].lived(), None);
+
+        assert!(tracker.diff(&current, later).is_empty());
+    }
+
+    #[test]
+    fn time_wait_keeps_the_connection() {
+        let start = SystemTime::now();
+        let mut tracker = Tracker::new();
+        tracker.diff(
+            &SocketsContainer::from_rows(vec![row(1, TcpState::Established, start)]),
+            start,
+        );
+
+        let mut time_wait = row(1, TcpState::TimeWait, start);
+        time_wait.inode = 0;
+        let events = tracker.diff(&SocketsContainer::from_rows(vec![time_wait]), start);
+
+        assert_eq!(events.len(), 1);
+        assert_eq!(
+            events[0].change,
+            Change::StateChanged {
+                from: TcpState::Established,
+                to: TcpState::TimeWait
+            }
+        );
+    }
+
+    #[test]
+    fn new_inode_on_the_same_address_is_a_new_socket() {
+        let start = SystemTime::now();
+        let later = start + Duration::from_secs(1);
+        let listener = |inode| SocketRow {
+            inode,
+            ..SocketRow::test_tcp("0.0.0.0:80", "0.0.0.0:0", TcpState::Listen)
+        };
+        let connection = |inode| SocketRow {
+            inode,
+            ..row(40000, TcpState::Established, start)
+        };
+        let mut tracker = Tracker::new();
+        tracker.diff(
+            &SocketsContainer::from_rows(vec![listener(10), connection(20)]),
+            start,
+        );
+
+        // the service restarted and a new connection reuses the 4-tuple
+        let events = tracker.diff(
+            &SocketsContainer::from_rows(vec![listener(11), connection(21)]),
+            later,
+        );
+        let changes: Vec<(Change, u32)> = events
+            .iter()
+            .map(|event| (event.change, event.socket.inode))
+            .collect();
+        assert_eq!(
+            changes,
+            vec![
+                (Change::Closed, 10),
+                (Change::Closed, 20),
+                (Change::Opened, 11),
+                (Change::Opened, 21),
+            ]
+        );
+    }
+
+    #[test]
+    fn destroyed_sockets_are_reported_once() {
+        let start = SystemTime::now();
+        let later = start + Duration::from_secs(1);
+        let mut tracker = Tracker::new();
+        let mut known = row(1, TcpState::Established, start);
+        known.pids = vec![42];
+        tracker.diff(&SocketsContainer::from_rows(vec![known]), start);
+
+        let mut destroyed = row(1, TcpState::Closed, later);
+        destroyed.inode = 0;
+        let event = tracker.destroyed(destroyed, later);
+        assert_eq!(event.change, Change::Closed);
+        assert_eq!(
+            (event.socket.inode, event.socket.pids.clone()),
+            (1, vec![42])
+        );
+        assert_eq!(event.lived(), Some(Duration::from_secs(1)));
+
+        let short_lived = tracker.destroyed(row(2, TcpState::Closed, later), later);
+        assert_eq!(short_lived.lived(), Some(Duration::from_secs(0)));
+
+        // what is left in TIME_WAIT was already reported closed
+        let mut time_wait = row(1, TcpState::TimeWait, later);
+        time_wait.inode = 0;
+        let remains = SocketsContainer::from_rows(vec![time_wait]);
+        assert!(tracker.diff(&remains, later).is_empty());
+        assert!(tracker.diff(&remains, later).is_empty());
+        assert!(tracker
+            .diff(&SocketsContainer::from_rows(vec![]), later)
+            .is_empty());
+    }
+
+    #[test]
+    fn destroyed_while_collecting_is_not_reopened() {
+        let start = SystemTime::now();
+        let mut tracker = Tracker::new();
+        tracker.diff(&SocketsContainer::from_rows(vec![]), start);
+
+        // the snapshot still lists the socket the kernel just destroyed
+        let event = tracker.destroyed(row(1, TcpState::Closed, start), start);
+        assert_eq!(event.change, Change::Closed);
+        let established = row(1, TcpState::Established, start);
+        assert!(tracker
+            .diff(&SocketsContainer::from_rows(vec![established]), start)
+            .is_empty());
+
+        let mut time_wait = row(1, TcpState::TimeWait, start);
+        time_wait.inode = 0;
+        assert!(tracker
+            .diff(&SocketsContainer::from_rows(vec![time_wait]), start)
+            .is_empty());
+        assert!(tracker
+            .diff(&SocketsContainer::from_rows(vec![]), start)
+            .is_empty());
+    }
+}
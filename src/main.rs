@@ -2,13 +2,35 @@
 extern crate failure;
 
 mod app;
+mod cli;
+mod collector;
 mod errors;
+mod export;
+mod filter;
+mod format;
+mod history;
+mod lifecycle;
+mod metrics;
+mod processes;
+mod recording;
+mod signal;
+mod sockets;
+mod sorting;
+mod source;
 mod ui;
 mod util;
 
 use app::App;
+use cli::{Command, Session};
+use collector::Collector;
+use errors::ConnectionToolsError;
+use export::Format;
+use recording::{Player, Recorder};
+use source::SourceKind;
 
 use std::io;
+use std::process;
+use std::time::Duration;
 use termion::event::Key;
 use termion::input::MouseTerminal;
 use termion::raw::IntoRawMode;
@@ -17,9 +39,77 @@ use tui::backend::TermionBackend;
 
 use tui::Terminal;
 
-use crate::util::event::{Event, Events};
+use crate::util::event::{Config, Event, Events};
 
 fn main() -> Result<(), failure::Error> {
+    let options = match cli::parse(std::env::args_os()) {
+        Ok(options) => options,
+        // help and version go to stdout, usage errors to stderr
+        Err(err) if err.use_stderr() => {
+            eprintln!("{}", err.message);
+            process::exit(cli::EXIT_ERROR);
+        }
+        Err(err) => {
+            println!("{}", err.message);
+            return Ok(());
+        }
+    };
+    match options.command {
+        Command::Tui(session) => {
+            // a bad recording is reported before the terminal is taken over
+            let started = start_session(options.source, session, options.interval);
+            let (app, collector) = match started {
+                Ok(started) => started,
+                Err(err) => {
+                    eprintln!("poke: {}", err);
+                    process::exit(cli::EXIT_ERROR);
+                }
+            };
+            run_tui(app, collector, options.format.unwrap_or(Format::Json))
+        }
+        Command::List(query) => process::exit(cli::run(options.source, options.format, &query)),
+        Command::Watch(filter) => process::exit(cli::watch(
+            options.source,
+            options.format,
+            &filter,
+            options.interval,
+        )),
+    }
+}
+
+/// The app and, unless it replays a recording, the collector feeding it.
+fn start_session(
+    source_kind: SourceKind,
+    session: Session,
+    interval: Duration,
+) -> Result<(App, Option<Collector>), ConnectionToolsError> {
+    match session {
+        Session::Live { record } => {
+            let recorder = match &record {
+                Some(path) => Some(Recorder::create(path, source_kind)?),
+                None => None,
+            };
+            // sock_diag can also report sockets as the kernel destroys them
+            let collector = Collector::spawn(
+                interval,
+                source_kind.create(),
+                source_kind == SourceKind::SockDiag,
+                recorder,
+            );
+            let mut app = App::new(source_kind);
+            app.refresh_interval = interval;
+            app.recording = record.map(|path| Ok(path.display().to_string()));
+            Ok((app, Some(collector)))
+        }
+        Session::Replay(path) => Ok((App::replaying(Player::load(&path)?), None)),
+    }
+}
+
+fn run_tui(
+    mut app: App,
+    collector: Option<Collector>,
+    export_format: Format,
+) -> Result<(), failure::Error> {
     // Terminal initialization
     let stdout = io::stdout().into_raw_mode()?;
     let stdout = MouseTerminal::from(stdout);
@@ -28,10 +118,12 @@ fn main() -> Result<(), failure::Error> {
     let mut terminal = Terminal::new(backend)?;
     terminal.hide_cursor()?;
 
-    let events = Events::new();
-
-    let mut app = App::new();
-    terminal.clear();
+    let mut refresh_interval = app.refresh_interval;
+    let events = Events::with_config(Config {
+        tick_rate: tick_rate(refresh_interval),
+    });
+    app.export_format = export_format;
+    terminal.clear()?;
 
     loop {
         terminal.draw(|mut f| ui::draw(&mut f, &mut app))?;
@@ -40,6 +132,12 @@ fn main() -> Result<(), failure::Error> {
                 Key::Char(c) => {
                     app.on_key(c);
                 }
+                Key::Backspace => {
+                    app.on_backspace();
+                }
+                Key::Esc => {
+                    app.on_esc();
+                }
                 Key::Up => {
                     app.on_up();
                 }
@@ -52,16 +150,37 @@ fn main() -> Result<(), failure::Error> {
                 Key::Right => {
                     app.on_right();
                 }
+                Key::BackTab => {
+                    app.on_back_tab();
+                }
                 _ => {}
             },
             Event::Tick => {
+                if let Some(collector) = &collector {
+                    while let Some(event) = collector.try_next() {
+                        app.on_collector_event(event);
+                    }
+                }
                 app.on_tick();
             }
         }
         if app.should_quit {
             break;
         }
+        if app.refresh_interval != refresh_interval {
+            refresh_interval = app.refresh_interval;
+            if let Some(collector) = &collector {
+                collector.set_interval(refresh_interval);
+            }
+            events.set_tick_rate(tick_rate(refresh_interval));
+        }
     }
 
     Ok(())
 }
+
+/// Snapshots are picked up on ticks, and the status line ages with them, so
+/// the screen is redrawn at least at the default rate.
+fn tick_rate(refresh_interval: Duration) -> Duration {
+    refresh_interval.min(Config::default().tick_rate)
+}
@@ -0,0 +1,172 @@
+//! Per-refresh socket counts kept for the Stats charts.
+
+use std::collections::HashSet;
+use std::time::SystemTime;
+
+use netstat2::TcpState;
+
+use crate::lifecycle::without_inode;
+use crate::sockets::{SocketKey, SocketsContainer};
+use crate::util::RingBuffer;
+
+/// TCP states with a line in the Stats chart.
+pub const CHARTED_STATES: [TcpState; 4] = [
+    TcpState::Established,
+    TcpState::Listen,
+    TcpState::TimeWait,
+    TcpState::CloseWait,
+];
+
+/// Samples kept, ten minutes at the default refresh interval.
+const HISTORY_LEN: usize = 2400;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Sample {
+    pub taken_at: SystemTime,
+    pub tcp: usize,
+    pub udp: usize,
+    /// Counts of `CHARTED_STATES`, in the same order.
+    pub states: [usize; CHARTED_STATES.len()],
+    /// TCP connections opened and closed per second since the previous sample.
+    pub opened: f64,
+    pub closed: f64,
+}
+
+pub struct Metrics {
+    samples: RingBuffer<Sample>,
+    /// Connections of the last sample, without inode, to tell the new and the
+    /// closed ones.
+    connections: HashSet<SocketKey>,
+}
+
+impl Metrics {
+    pub fn new() -> Self {
+        Metrics {
+            samples: RingBuffer::new(HISTORY_LEN),
+            connections: HashSet::new(),
+        }
+    }
+
+    /// The first sample has nothing to compare with and reports no opened
+    /// or closed connections.
+    pub fn record(&mut self, sockets: &SocketsContainer, taken_at: SystemTime) {
+        let connections: HashSet<SocketKey> = sockets
+            .tcp_sockets
+            .iter()
+            .filter(|row| row.state != Some(TcpState::Listen))
+            .map(|row| without_inode(&row.key()))
+            .collect();
+
+        let elapsed = self.samples.last().and_then(|last| {
+            taken_at
+                .duration_since(last.taken_at)
+                .ok()
+                .map(|elapsed| elapsed.as_secs_f64())
+                .filter(|seconds| *seconds > 0.0)
+        });
+        let (opened, closed) = match elapsed {
+            Some(seconds) => (
+                connections.difference(&self.connections).count() as f64 / seconds,
+                self.connections.difference(&connections).count() as f64 / seconds,
+            ),
+            None => (0.0, 0.0),
+        };
+
+        let mut states = [0; CHARTED_STATES.len()];
+        for row in &sockets.tcp_sockets {
+            if let Some(index) = CHARTED_STATES
+                .iter()
+                .position(|state| Some(*state) == row.state)
+            {
+                states[index] += 1;
+            }
+        }
+
+        self.samples.push(Sample {
+            taken_at,
+            tcp: sockets.tcp_sockets.len(),
+            udp: sockets.udp_sockets.len(),
+            states,
+            opened,
+            closed,
+        });
+        self.connections = connections;
+    }
+
+    /// Oldest sample first.
+    pub fn samples(&self) -> impl DoubleEndedIterator<Item = &Sample> + ExactSizeIterator {
+        self.samples.iter()
+    }
+
+    pub fn last(&self) -> Option<&Sample> {
+        self.samples.last()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Metrics;
+    use crate::sockets::{SocketRow, SocketsContainer};
+    use netstat2::TcpState;
+    use std::time::{Duration, SystemTime};
+
+    fn row(local_port: u16, state: TcpState) -> SocketRow {
+        SocketRow::test_tcp(&format!("127.0.0.1:{}", local_port), "10.0.0.1:443", state)
+    }
+
+    #[test]
+    fn opened_and_closed_per_second() {
+        let start = SystemTime::now();
+        let mut metrics = Metrics::new();
+
+        metrics.record(
+            &SocketsContainer::from_rows(vec![
+                row(80, TcpState::Listen),
+                row(1, TcpState::Established),
+                row(2, TcpState::CloseWait),
+            ]),
+            start,
+        );
+        let first = metrics.last().unwrap().clone();
+        assert_eq!((first.opened, first.closed), (0.0, 0.0));
+        assert_eq!(first.states, [1, 1, 0, 1]);
+
+        metrics.record(
+            &SocketsContainer::from_rows(vec![
+                row(80, TcpState::Listen),
+                row(2, TcpState::CloseWait),
+                row(3, TcpState::Established),
+                row(4, TcpState::Established),
+            ]),
+            start + Duration::from_millis(500),
+        );
+        let second = metrics.last().unwrap();
+        assert_eq!((second.opened, second.closed), (4.0, 2.0));
+        assert_eq!(second.tcp, 4);
+        assert_eq!(metrics.samples().len(), 2);
+    }
+
+    #[test]
+    fn time_wait_is_not_a_new_connection() {
+        let start = SystemTime::now();
+        let second = Duration::from_secs(1);
+        let mut metrics = Metrics::new();
+        metrics.record(
+            &SocketsContainer::from_rows(vec![row(1, TcpState::Established)]),
+            start,
+        );
+
+        let mut time_wait = row(1, TcpState::TimeWait);
+        time_wait.inode = 0;
+        metrics.record(
+            &SocketsContainer::from_rows(vec![time_wait]),
+            start + second,
+        );
+        let last = metrics.last().unwrap();
+        assert_eq!((last.opened, last.closed), (0.0, 0.0));
+
+        metrics.record(&SocketsContainer::from_rows(vec![]), start + second * 2);
+        let last = metrics.last().unwrap();
+        assert_eq!((last.opened, last.closed), (0.0, 1.0));
+    }
+}
@@ -0,0 +1,209 @@
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Deserializer, Serialize};
+use sysinfo::{ProcessExt, RefreshKind, System, SystemExt};
+
+/// A process is identified by its pid together with its start time, so a pid
+/// reused by the kernel is not mistaken for the process that owned it before.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct ProcessKey {
+    pub pid: u32,
+    /// Clock ticks since boot, as found in `/proc/<pid>/stat`.
+    pub start_ticks: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProcessInfo {
+    pub key: ProcessKey,
+    pub name: String,
+    pub status: String,
+    pub cmd: Vec<String>,
+    pub exe: PathBuf,
+    /// Left out of recordings, which are meant to be shared and environments
+    /// often hold secrets.
+    #[serde(skip)]
+    pub environ: Vec<String>,
+    pub memory: u64,
+    pub virtual_memory: u64,
+    pub start_time: u64,
+    /// NaN until sysinfo has two samples to compare, which JSON writes as
+    /// `null`.
+    #[serde(deserialize_with = "null_as_nan")]
+    pub cpu_usage: f32,
+}
+
+fn null_as_nan<'de, D: Deserializer<'de>>(deserializer: D) -> Result<f32, D::Error> {
+    Ok(Option::<f32>::deserialize(deserializer)?.unwrap_or(f32::NAN))
+}
+
+/// Exited processes sysinfo may still hold on to, past which it is started
+/// over to bound its memory.
+const FORGOTTEN_LIMIT: usize = 4096;
+
+/// Details of the processes that own sockets.
+///
+/// A process is looked up as soon as it shows up, but the details of known
+/// processes (memory, CPU) are only refreshed once per `refresh_interval`.
+pub struct ProcessCache {
+    system: System,
+    processes: HashMap<u32, ProcessInfo>,
+    refresh_interval: Duration,
+    last_refresh: Option<Instant>,
+    /// Pids dropped from the cache since `system` was created.
+    forgotten: usize,
+}
+
+impl ProcessCache {
+    pub fn new(refresh_interval: Duration) -> Self {
+        ProcessCache {
+            system: new_system(),
+            processes: HashMap::new(),
+            refresh_interval,
+            last_refresh: None,
+            forgotten: 0,
+        }
+    }
+
+    pub fn update(&mut self, pids: &HashSet<u32>) {
+        let mut due = self
+            .last_refresh
+            .map(|last_refresh| last_refresh.elapsed() >= self.refresh_interval)
+            .unwrap_or(true);
+
+        let mut reused = false;
+        let known = self.processes.len();
+        self.processes.retain(|pid, info| {
+            if !pids.contains(pid) {
+                return false;
+            }
+            match read_start_ticks(*pid) {
+                Some(start_ticks) if start_ticks == info.key.start_ticks => true,
+                Some(_) => {
+                    reused = true;
+                    false
+                }
+                None => false,
+            }
+        });
+        self.forgotten += known - self.processes.len();
+        if reused || self.forgotten > FORGOTTEN_LIMIT {
+            // sysinfo never forgets a pid and doesn't reread the name of one it
+            // already knows, so a reused pid means starting over; every process
+            // then needs a new sample for its CPU usage.
+            self.system = new_system();
+            self.forgotten = 0;
+            due = true;
+        }
+
+        for &pid in pids {
+            if due || !self.processes.contains_key(&pid) {
+                self.refresh_process(pid);
+            }
+        }
+        if due {
+            self.last_refresh = Some(Instant::now());
+        }
+    }
+
+    pub fn name(&self, pid: u32) -> Option<&str> {
+        self.processes.get(&pid).map(|info| info.name.as_str())
+    }
+
+    pub fn snapshot(&self) -> HashMap<u32, ProcessInfo> {
+        self.processes.clone()
+    }
+
+    fn refresh_process(&mut self, pid: u32) {
+        let start_ticks = match read_start_ticks(pid) {
+            Some(start_ticks) => start_ticks,
+            None => {
+                self.processes.remove(&pid);
+                return;
+            }
+        };
+
+        self.system.refresh_process(pid as i32);
+        match self.system.get_process(pid as i32) {
+            Some(proc_) => {
+                let info = ProcessInfo {
+                    key: ProcessKey { pid, start_ticks },
+                    name: proc_.name().to_owned(),
+                    status: format!("{:?}", proc_.status()),
+                    cmd: proc_.cmd().to_vec(),
+                    exe: proc_.exe().to_path_buf(),
+                    environ: proc_.environ().to_vec(),
+                    memory: proc_.memory(),
+                    virtual_memory: proc_.virtual_memory(),
+                    start_time: proc_.start_time(),
+                    cpu_usage: proc_.cpu_usage(),
+                };
+                self.processes.insert(pid, info);
+            }
+            None => {
+                self.processes.remove(&pid);
+            }
+        }
+    }
+}
+
+fn new_system() -> System {
+    // processors have to be known for sysinfo to compute per-process CPU usage
+    System::new_with_specifics(RefreshKind::new().with_cpu())
+}
+
+pub fn read_start_ticks(pid: u32) -> Option<u64> {
+    fs::read_to_string(format!("/proc/{}/stat", pid))
+        .ok()
+        .and_then(|stat| parse_start_ticks(&stat))
+}
+
+/// `starttime` is the 22nd field of `/proc/<pid>/stat`. The 2nd field is the
+/// command name in parentheses and may itself contain spaces and parentheses.
+fn parse_start_ticks(stat: &str) -> Option<u64> {
+    let after_comm = &stat[stat.rfind(')')? + 1..];
+    after_comm.split_whitespace().nth(19)?.parse().ok()
+}
+
+#[cfg(test)]
+mod test {
+    use super::{parse_start_ticks, ProcessCache};
+    use std::collections::HashSet;
+    use std::process::Command;
+    use std::thread;
+    use std::time::Duration;
+
+    #[test]
+    fn start_ticks_from_stat() {
+        let stat = "1234 (my (odd) proc) S 1 1234 1234 0 -1 4194560 1160 0 0 0 \
+                    12 4 0 0 20 0 1 0 98765 12345678 1234 18446744073709551615";
+
+        assert_eq!(parse_start_ticks(stat), Some(98765));
+        assert_eq!(parse_start_ticks("1234 (truncated) S 1"), None);
+    }
+
+    #[test]
+    fn exited_processes_are_forgotten_alone() {
+        let mut exiting = Command::new("sleep").arg("30").spawn().unwrap();
+        let mut staying = Command::new("sleep").arg("30").spawn().unwrap();
+        // the children have no name of their own until they exec
+        thread::sleep(Duration::from_millis(100));
+        let pids: HashSet<u32> = vec![exiting.id(), staying.id()].into_iter().collect();
+        let mut cache = ProcessCache::new(Duration::from_secs(60));
+        cache.update(&pids);
+        assert_eq!(cache.name(staying.id()), Some("sleep"));
+
+        exiting.kill().unwrap();
+        exiting.wait().unwrap();
+        cache.update(&pids);
+        assert_eq!(cache.name(exiting.id()), None);
+        assert_eq!(cache.name(staying.id()), Some("sleep"));
+        // the others keep their CPU baseline
+        assert_eq!(cache.forgotten, 1);
+
+        staying.kill().unwrap();
+        staying.wait().unwrap();
+    }
+}
@@ -0,0 +1,271 @@
+//! Sessions recorded to a file and played back in the TUI.
+//!
+//! A recording is gzip compressed NDJSON: a header line, then one snapshot
+//! per line as the collector took it. The stream is flushed after every
+//! snapshot, so a recording cut short by a crash or a `kill` still plays
+//! back up to its last snapshot.
+
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime};
+
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use serde::{Deserialize, Serialize};
+
+use crate::collector::Snapshot;
+use crate::errors::ConnectionToolsError;
+use crate::source::SourceKind;
+
+const VERSION: u32 = 1;
+
+#[derive(Serialize, Deserialize)]
+struct Header {
+    version: u32,
+    /// Name of the source the sockets were listed with.
+    source: String,
+}
+
+pub struct Recorder {
+    path: PathBuf,
+    out: GzEncoder<BufWriter<File>>,
+}
+
+impl Recorder {
+    /// Creates the file at `path`, replacing any file already there.
+    pub fn create(path: &Path, source: SourceKind) -> Result<Self, ConnectionToolsError> {
+        let file = File::create(path).map_err(|err| ConnectionToolsError::Record {
+            message: format!("{}: {}", path.display(), err),
+        })?;
+        let mut recorder = Recorder {
+            path: path.to_owned(),
+            out: GzEncoder::new(BufWriter::new(file), Compression::default()),
+        };
+        recorder.write_line(&Header {
+            version: VERSION,
+            source: source.name().to_owned(),
+        })?;
+        Ok(recorder)
+    }
+
+    pub fn record(&mut self, snapshot: &Snapshot) -> Result<(), ConnectionToolsError> {
+        self.write_line(snapshot)
+    }
+
+    fn write_line<T: Serialize>(&mut self, value: &T) -> Result<(), ConnectionToolsError> {
+        let out = &mut self.out;
+        serde_json::to_writer(&mut *out, value)
+            .map_err(io::Error::from)
+            .and_then(|_| out.write_all(b"\n"))
+            .and_then(|_| out.flush())
+            .map_err(|err| ConnectionToolsError::Record {
+                message: format!("{}: {}", self.path.display(), err),
+            })
+    }
+}
+
+/// Plays a recording back at the pace it was recorded, or one snapshot at a
+/// time while paused.
+pub struct Player {
+    pub source: SourceKind,
+    snapshots: Vec<Snapshot>,
+    /// Index of the snapshot on screen.
+    position: usize,
+    pub paused: bool,
+    /// The recorded time that was on screen at an instant, to know which
+    /// snapshot is due next.
+    anchor: (SystemTime, Instant),
+}
+
+impl Player {
+    /// Reads the whole recording at `path` into memory.
+    pub fn load(path: &Path) -> Result<Self, ConnectionToolsError> {
+        let replay_error = |message: String| ConnectionToolsError::Replay {
+            message: format!("{}: {}", path.display(), message),
+        };
+        let file = File::open(path).map_err(|err| replay_error(err.to_string()))?;
+        let mut lines = BufReader::new(GzDecoder::new(file)).lines();
+
+        let header = lines
+            .next()
+            .unwrap_or_else(|| Err(io::ErrorKind::UnexpectedEof.into()))
+            .map_err(|err| replay_error(err.to_string()))?;
+        let header: Header = serde_json::from_str(&header)
+            .map_err(|_| replay_error("not a poke recording".to_owned()))?;
+        if header.version != VERSION {
+            return Err(replay_error(format!(
+                "unsupported recording version {}",
+                header.version
+            )));
+        }
+        let source = header
+            .source
+            .parse()
+            .map_err(|err| replay_error(format!("{}", err)))?;
+
+        let mut snapshots = Vec::new();
+        for (number, line) in lines.enumerate() {
+            let line = match line {
+                Ok(line) => line,
+                // the recording was cut short
+                Err(ref err) if err.kind() == io::ErrorKind::UnexpectedEof => break,
+                Err(err) => return Err(replay_error(err.to_string())),
+            };
+            let snapshot = serde_json::from_str(&line)
+                .map_err(|err| replay_error(format!("snapshot {}: {}", number + 1, err)))?;
+            snapshots.push(snapshot);
+        }
+        if snapshots.is_empty() {
+            return Err(replay_error("no snapshots recorded".to_owned()));
+        }
+        Ok(Player::new(source, snapshots, Instant::now()))
+    }
+
+    /// Plays `snapshots`, of which there is at least one, from `now` on.
+    pub fn new(source: SourceKind, snapshots: Vec<Snapshot>, now: Instant) -> Self {
+        Player {
+            source,
+            anchor: (snapshots[0].taken_at, now),
+            snapshots,
+            position: 0,
+            paused: false,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.snapshots.len()
+    }
+
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    pub fn snapshot(&self, index: usize) -> &Snapshot {
+        &self.snapshots[index]
+    }
+
+    /// Moves on to the snapshot due at `now` while playing; returns the
+    /// previous position if it moved.
+    pub fn tick(&mut self, now: Instant) -> Option<usize> {
+        if self.paused {
+            return None;
+        }
+        let (recorded, at) = self.anchor;
+        let playing = recorded + now.saturating_duration_since(at);
+        let due = self.position
+            + self.snapshots[self.position + 1..]
+                .iter()
+                .take_while(|snapshot| snapshot.taken_at <= playing)
+                .count();
+        self.move_to(due, None)
+    }
+
+    pub fn toggle_pause(&mut self, now: Instant) {
+        self.paused = !self.paused;
+        self.anchor = (self.snapshots[self.position].taken_at, now);
+    }
+
+    /// Pauses and shows the next or the previous snapshot.
+    pub fn step(&mut self, forward: bool, now: Instant) -> Option<usize> {
+        self.paused = true;
+        let position = if forward {
+            (self.position + 1).min(self.len() - 1)
+        } else {
+            self.position.saturating_sub(1)
+        };
+        self.move_to(position, Some(now))
+    }
+
+    /// Jumps to the last snapshot taken `by` before or after the one on
+    /// screen.
+    pub fn seek(&mut self, by: Duration, forward: bool, now: Instant) -> Option<usize> {
+        let current = self.snapshots[self.position].taken_at;
+        let target = if forward {
+            current + by
+        } else {
+            current.checked_sub(by).unwrap_or(current)
+        };
+        let position = self
+            .snapshots
+            .partition_point(|snapshot| snapshot.taken_at <= target)
+            .saturating_sub(1);
+        self.move_to(position, Some(now))
+    }
+
+    fn move_to(&mut self, position: usize, now: Option<Instant>) -> Option<usize> {
+        let previous = self.position;
+        self.position = position;
+        if let Some(now) = now {
+            self.anchor = (self.snapshots[position].taken_at, now);
+        }
+        Some(previous).filter(|previous| *previous != position)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Player, Recorder};
+    use crate::collector::Snapshot;
+    use crate::sockets::SocketsContainer;
+    use crate::source::SourceKind;
+    use std::collections::HashMap;
+    use std::fs;
+    use std::mem;
+    use std::time::{Duration, Instant, SystemTime};
+
+    fn snapshots(count: u64) -> Vec<Snapshot> {
+        let start = SystemTime::now();
+        (0..count)
+            .map(|second| Snapshot {
+                sockets: Ok(SocketsContainer::new()),
+                processes: HashMap::new(),
+                taken_at: start + Duration::from_secs(second),
+                duration: Duration::from_millis(second),
+                events: Vec::new(),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn interrupted_recording_plays_back() {
+        let path = std::env::temp_dir().join(format!("poke-test-{}.ndjson.gz", std::process::id()));
+        let mut recorder = Recorder::create(&path, SourceKind::Procfs).unwrap();
+        for snapshot in snapshots(3) {
+            recorder.record(&snapshot).unwrap();
+        }
+        // as if poke was killed: the gzip stream is never finished
+        mem::forget(recorder);
+
+        let player = Player::load(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(player.source, SourceKind::Procfs);
+        assert_eq!(player.len(), 3);
+        assert_eq!(player.snapshot(2).duration, Duration::from_millis(2));
+    }
+
+    #[test]
+    fn plays_at_the_recorded_pace() {
+        let start = Instant::now();
+        let mut player = Player::new(SourceKind::default(), snapshots(30), start);
+
+        assert_eq!(player.tick(start + Duration::from_millis(500)), None);
+        assert_eq!(player.tick(start + Duration::from_millis(2500)), Some(0));
+        assert_eq!(player.position(), 2);
+
+        player.toggle_pause(start);
+        assert_eq!(player.tick(start + Duration::from_secs(5)), None);
+        assert_eq!(player.step(true, start), Some(2));
+        assert_eq!(player.step(false, start), Some(3));
+        assert_eq!(player.position(), 2);
+
+        assert_eq!(player.seek(Duration::from_secs(10), true, start), Some(2));
+        assert_eq!(player.position(), 12);
+        assert_eq!(player.seek(Duration::from_secs(60), true, start), Some(12));
+        assert_eq!(player.position(), 29);
+        assert_eq!(player.step(true, start), None);
+        assert_eq!(player.seek(Duration::from_secs(60), false, start), Some(29));
+        assert_eq!(player.position(), 0);
+    }
+}
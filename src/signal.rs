@@ -0,0 +1,138 @@
+//! Signals sent from the TUI to the processes owning a socket.
+
+use std::convert::TryFrom;
+use std::fmt;
+use std::io;
+
+use crate::errors::ConnectionToolsError;
+use crate::processes::{read_start_ticks, ProcessKey};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Signal {
+    Term,
+    Kill,
+    Hup,
+    Int,
+}
+
+impl Signal {
+    pub const ALL: [Signal; 4] = [Signal::Term, Signal::Kill, Signal::Hup, Signal::Int];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Signal::Term => "TERM",
+            Signal::Kill => "KILL",
+            Signal::Hup => "HUP",
+            Signal::Int => "INT",
+        }
+    }
+
+    fn number(self) -> libc::c_int {
+        match self {
+            Signal::Term => libc::SIGTERM,
+            Signal::Kill => libc::SIGKILL,
+            Signal::Hup => libc::SIGHUP,
+            Signal::Int => libc::SIGINT,
+        }
+    }
+
+    pub fn next(self) -> Signal {
+        let index = Signal::ALL.iter().position(|signal| *signal == self);
+        Signal::ALL[(index.unwrap_or(0) + 1) % Signal::ALL.len()]
+    }
+
+    pub fn previous(self) -> Signal {
+        let index = Signal::ALL.iter().position(|signal| *signal == self);
+        Signal::ALL[(index.unwrap_or(0) + Signal::ALL.len() - 1) % Signal::ALL.len()]
+    }
+}
+
+impl fmt::Display for Signal {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+/// Sends `signal` to every process, skipping those that exited since they
+/// were listed: a pid the kernel gave to another process is left alone.
+/// Failures don't stop the other processes from being signalled.
+pub fn send(signal: Signal, processes: &[ProcessKey]) -> Result<(), ConnectionToolsError> {
+    let failures: Vec<String> = processes
+        .iter()
+        .filter_map(|process| {
+            send_one(signal, *process)
+                .err()
+                .map(|err| format!("pid {}: {}", process.pid, err))
+        })
+        .collect();
+    if failures.is_empty() {
+        Ok(())
+    } else {
+        Err(ConnectionToolsError::Signal {
+            message: format!("{} to {}", signal, failures.join(", ")),
+        })
+    }
+}
+
+fn send_one(signal: Signal, process: ProcessKey) -> io::Result<()> {
+    if process.pid == std::process::id() {
+        return Err(io::Error::other("that is poke itself"));
+    }
+    // 0 and negative pids would signal whole process groups
+    let pid = match libc::pid_t::try_from(process.pid) {
+        Ok(pid) if pid > 0 => pid,
+        _ => return Err(io::Error::from_raw_os_error(libc::ESRCH)),
+    };
+    if read_start_ticks(process.pid) != Some(process.start_ticks) {
+        return Err(io::Error::from_raw_os_error(libc::ESRCH));
+    }
+    // SAFETY: kill has no memory safety requirements
+    if unsafe { libc::kill(pid, signal.number()) } == -1 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::{send, Signal};
+    use crate::processes::{read_start_ticks, ProcessKey};
+    use std::os::unix::process::ExitStatusExt;
+    use std::process::Command;
+
+    #[test]
+    fn signals_cycle() {
+        assert_eq!(Signal::Int.next(), Signal::Term);
+        assert_eq!(Signal::Term.previous(), Signal::Int);
+        assert_eq!(Signal::Kill.next().to_string(), "HUP");
+    }
+
+    #[test]
+    fn only_the_listed_process_is_signalled() {
+        let mut child = Command::new("sleep").arg("30").spawn().unwrap();
+        let pid = child.id();
+        let start_ticks = read_start_ticks(pid).unwrap();
+
+        let reused = ProcessKey {
+            pid,
+            start_ticks: start_ticks + 1,
+        };
+        let err = send(Signal::Term, &[reused]).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            format!(
+                "can't send signal: TERM to pid {}: No such process (os error 3)",
+                pid
+            )
+        );
+
+        let myself = ProcessKey {
+            pid: std::process::id(),
+            start_ticks: 0,
+        };
+        assert!(send(Signal::Kill, &[myself]).is_err());
+
+        send(Signal::Kill, &[ProcessKey { pid, start_ticks }]).unwrap();
+        assert_eq!(child.wait().unwrap().signal(), Some(libc::SIGKILL));
+    }
+}
@@ -0,0 +1,699 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::iter::Sum;
+use std::net::IpAddr;
+#[cfg(test)]
+use std::net::SocketAddr;
+use std::ops::Add;
+use std::time::{Duration, SystemTime};
+
+use netstat2::{ProtocolSocketInfo, SocketInfo, TcpState};
+use serde::{Deserialize, Serialize};
+
+use crate::lifecycle::without_inode;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Protocol {
+    Tcp,
+    Udp,
+}
+
+impl fmt::Display for Protocol {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                Protocol::Tcp => "TCP",
+                Protocol::Udp => "UDP",
+            }
+        )
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Family {
+    Ipv4,
+    Ipv6,
+}
+
+impl Family {
+    pub fn of(addr: &IpAddr) -> Self {
+        match addr {
+            IpAddr::V4(_) => Family::Ipv4,
+            IpAddr::V6(_) => Family::Ipv6,
+        }
+    }
+}
+
+impl fmt::Display for Family {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                Family::Ipv4 => "IPv4",
+                Family::Ipv6 => "IPv6",
+            }
+        )
+    }
+}
+
+/// Identity of a socket: stays the same across refreshes as long as
+/// the kernel keeps the socket alive.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct SocketKey {
+    pub protocol: Protocol,
+    pub local_addr: IpAddr,
+    pub local_port: u16,
+    pub remote_addr: Option<IpAddr>,
+    pub remote_port: Option<u16>,
+    pub inode: u32,
+}
+
+/// Identity of any socket poke lists.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum SocketId {
+    Inet(SocketKey),
+    /// Unix sockets have no addresses to tell them apart, only inodes.
+    Unix(u32),
+    Raw(RawKind, u32),
+}
+
+/// Bytes waiting in the socket queues. For a listening socket `recv` is the
+/// number of connections waiting to be accepted instead.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Queues {
+    pub recv: u32,
+    pub send: u32,
+}
+
+/// The part of the kernel's `struct tcp_info` that `ss -ti` is usually run for.
+/// Counters missing on older kernels are `None`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TcpInfo {
+    /// Smoothed round trip time, in microseconds.
+    pub rtt_us: u32,
+    pub rttvar_us: u32,
+    /// Congestion window, in segments.
+    pub snd_cwnd: u32,
+    /// Retransmitted segments over the lifetime of the connection.
+    pub retransmits: u32,
+    /// Segments currently considered lost.
+    pub lost: u32,
+    pub bytes_acked: Option<u64>,
+    pub bytes_received: Option<u64>,
+    /// Bytes per second.
+    pub delivery_rate: Option<u64>,
+}
+
+/// Socket memory as reported by `SK_MEMINFO_*`, in bytes.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SocketMemory {
+    pub rmem_alloc: u32,
+    pub rcvbuf: u32,
+    pub wmem_alloc: u32,
+    pub sndbuf: u32,
+    pub fwd_alloc: u32,
+    pub wmem_queued: u32,
+    pub optmem: u32,
+    pub backlog: u32,
+    /// Packets dropped before they were queued, a count rather than bytes.
+    pub drops: u32,
+}
+
+/// Bytes per second a socket received and sent since the previous refresh.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Throughput {
+    pub recv: u64,
+    pub send: u64,
+}
+
+impl Throughput {
+    pub fn total(&self) -> u64 {
+        self.recv + self.send
+    }
+}
+
+impl Add for Throughput {
+    type Output = Throughput;
+
+    fn add(self, other: Throughput) -> Throughput {
+        Throughput {
+            recv: self.recv + other.recv,
+            send: self.send + other.send,
+        }
+    }
+}
+
+impl Sum for Throughput {
+    fn sum<I: Iterator<Item = Throughput>>(iter: I) -> Throughput {
+        iter.fold(Throughput::default(), Add::add)
+    }
+}
+
+/// `bytes_received` and `bytes_acked` of the sockets that report them.
+pub type ByteCounters = HashMap<SocketKey, (u64, u64)>;
+
+/// One socket as poke shows it, independent of the way it was collected.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SocketRow {
+    pub protocol: Protocol,
+    pub family: Family,
+    pub local_addr: IpAddr,
+    pub local_port: u16,
+    pub remote_addr: Option<IpAddr>,
+    pub remote_port: Option<u16>,
+    #[serde(with = "tcp_state::option")]
+    pub state: Option<TcpState>,
+    pub pids: Vec<u32>,
+    pub inode: u32,
+    pub uid: Option<u32>,
+    pub process_name: Option<String>,
+    pub first_seen: SystemTime,
+    pub queues: Option<Queues>,
+    pub tcp_info: Option<TcpInfo>,
+    pub memory: Option<SocketMemory>,
+    /// Only known for sockets that report byte counters and were already
+    /// there at the previous refresh.
+    pub throughput: Option<Throughput>,
+}
+
+impl SocketRow {
+    pub fn from_socket_info(socket_info: SocketInfo, now: SystemTime) -> Self {
+        let SocketInfo {
+            protocol_socket_info,
+            associated_pids,
+            inode,
+        } = socket_info;
+
+        match protocol_socket_info {
+            ProtocolSocketInfo::Tcp(tcp_si) => SocketRow {
+                protocol: Protocol::Tcp,
+                family: Family::of(&tcp_si.local_addr),
+                local_addr: tcp_si.local_addr,
+                local_port: tcp_si.local_port,
+                remote_addr: Some(tcp_si.remote_addr),
+                remote_port: Some(tcp_si.remote_port),
+                state: Some(tcp_si.state),
+                pids: associated_pids,
+                inode,
+                uid: None,
+                process_name: None,
+                first_seen: now,
+                queues: None,
+                tcp_info: None,
+                memory: None,
+                throughput: None,
+            },
+            ProtocolSocketInfo::Udp(udp_si) => SocketRow {
+                protocol: Protocol::Udp,
+                family: Family::of(&udp_si.local_addr),
+                local_addr: udp_si.local_addr,
+                local_port: udp_si.local_port,
+                remote_addr: None,
+                remote_port: None,
+                state: None,
+                pids: associated_pids,
+                inode,
+                uid: None,
+                process_name: None,
+                first_seen: now,
+                queues: None,
+                tcp_info: None,
+                memory: None,
+                throughput: None,
+            },
+        }
+    }
+
+    pub fn key(&self) -> SocketKey {
+        SocketKey {
+            protocol: self.protocol,
+            local_addr: self.local_addr,
+            local_port: self.local_port,
+            remote_addr: self.remote_addr,
+            remote_port: self.remote_port,
+            inode: self.inode,
+        }
+    }
+}
+
+#[cfg(test)]
+impl SocketRow {
+    /// A TCP socket between two `addr:port` endpoints, first seen now, with
+    /// its local port as inode and no owner.
+    pub fn test_tcp(local: &str, remote: &str, state: TcpState) -> Self {
+        let local: SocketAddr = local.parse().unwrap();
+        let remote: SocketAddr = remote.parse().unwrap();
+        SocketRow {
+            protocol: Protocol::Tcp,
+            family: Family::of(&local.ip()),
+            local_addr: local.ip(),
+            local_port: local.port(),
+            remote_addr: Some(remote.ip()),
+            remote_port: Some(remote.port()),
+            state: Some(state),
+            pids: vec![],
+            inode: u32::from(local.port()),
+            uid: None,
+            process_name: None,
+            first_seen: SystemTime::now(),
+            queues: None,
+            tcp_info: None,
+            memory: None,
+            throughput: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum UnixType {
+    Stream,
+    Dgram,
+    SeqPacket,
+}
+
+impl fmt::Display for UnixType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                UnixType::Stream => "STREAM",
+                UnixType::Dgram => "DGRAM",
+                UnixType::SeqPacket => "SEQPACKET",
+            }
+        )
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum UnixState {
+    Listen,
+    Unconnected,
+    Connecting,
+    Connected,
+    Disconnecting,
+}
+
+impl fmt::Display for UnixState {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                UnixState::Listen => "LISTEN",
+                UnixState::Unconnected => "UNCONNECTED",
+                UnixState::Connecting => "CONNECTING",
+                UnixState::Connected => "CONNECTED",
+                UnixState::Disconnecting => "DISCONNECTING",
+            }
+        )
+    }
+}
+
+/// A Unix domain socket.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UnixSocketRow {
+    pub socket_type: UnixType,
+    /// Bound path; abstract names start with `@`, as `ss` shows them.
+    pub path: Option<String>,
+    pub state: UnixState,
+    pub inode: u32,
+    /// Inode of the socket at the other end of a connection.
+    pub peer_inode: Option<u32>,
+    pub pids: Vec<u32>,
+    pub process_name: Option<String>,
+    pub first_seen: SystemTime,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum RawKind {
+    /// `SOCK_RAW` IP sockets, e.g. ping or a routing daemon.
+    Ip,
+    /// `AF_PACKET` sockets, which see whole frames; what sniffers open.
+    Packet,
+    Netlink,
+}
+
+impl fmt::Display for RawKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                RawKind::Ip => "RAW",
+                RawKind::Packet => "PACKET",
+                RawKind::Netlink => "NETLINK",
+            }
+        )
+    }
+}
+
+/// A raw IP, packet or netlink socket.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RawSocketRow {
+    pub kind: RawKind,
+    /// IP protocol, ethertype or netlink family, depending on the kind.
+    pub protocol: u16,
+    /// Bound address, interface or netlink port id, depending on the kind.
+    pub endpoint: String,
+    pub uid: Option<u32>,
+    pub inode: u32,
+    pub pids: Vec<u32>,
+    pub process_name: Option<String>,
+    pub first_seen: SystemTime,
+}
+
+impl RawSocketRow {
+    pub fn protocol_name(&self) -> String {
+        let protocol = i32::from(self.protocol);
+        let name = match self.kind {
+            RawKind::Ip => match protocol {
+                libc::IPPROTO_ICMP => Some("icmp"),
+                libc::IPPROTO_IGMP => Some("igmp"),
+                libc::IPPROTO_TCP => Some("tcp"),
+                libc::IPPROTO_UDP => Some("udp"),
+                libc::IPPROTO_ICMPV6 => Some("icmpv6"),
+                libc::IPPROTO_RAW => Some("raw"),
+                _ => None,
+            },
+            RawKind::Packet => match protocol {
+                libc::ETH_P_ALL => Some("all"),
+                libc::ETH_P_IP => Some("ip"),
+                libc::ETH_P_ARP => Some("arp"),
+                libc::ETH_P_IPV6 => Some("ipv6"),
+                _ => None,
+            },
+            RawKind::Netlink => match protocol {
+                libc::NETLINK_ROUTE => Some("route"),
+                libc::NETLINK_USERSOCK => Some("usersock"),
+                libc::NETLINK_SOCK_DIAG => Some("sock_diag"),
+                libc::NETLINK_NFLOG => Some("nflog"),
+                libc::NETLINK_XFRM => Some("xfrm"),
+                libc::NETLINK_SELINUX => Some("selinux"),
+                libc::NETLINK_AUDIT => Some("audit"),
+                libc::NETLINK_CONNECTOR => Some("connector"),
+                libc::NETLINK_NETFILTER => Some("netfilter"),
+                libc::NETLINK_KOBJECT_UEVENT => Some("uevent"),
+                libc::NETLINK_GENERIC => Some("generic"),
+                _ => None,
+            },
+        };
+        match (name, self.kind) {
+            (Some(name), _) => name.to_owned(),
+            (None, RawKind::Packet) => format!("0x{:04x}", self.protocol),
+            (None, _) => self.protocol.to_string(),
+        }
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct SocketsContainer {
+    pub tcp_sockets: Vec<SocketRow>,
+    pub udp_sockets: Vec<SocketRow>,
+    pub unix_sockets: Vec<UnixSocketRow>,
+    pub raw_sockets: Vec<RawSocketRow>,
+}
+
+impl SocketsContainer {
+    pub fn new() -> Self {
+        SocketsContainer {
+            tcp_sockets: Vec::new(),
+            udp_sockets: Vec::new(),
+            unix_sockets: Vec::new(),
+            raw_sockets: Vec::new(),
+        }
+    }
+
+    pub fn from_rows(rows: Vec<SocketRow>) -> Self {
+        let (tcp_sockets, udp_sockets) = rows
+            .into_iter()
+            .partition(|row| row.protocol == Protocol::Tcp);
+
+        SocketsContainer {
+            tcp_sockets,
+            udp_sockets,
+            unix_sockets: Vec::new(),
+            raw_sockets: Vec::new(),
+        }
+    }
+
+    pub fn rows(&self) -> impl Iterator<Item = &SocketRow> {
+        self.tcp_sockets.iter().chain(self.udp_sockets.iter())
+    }
+
+    pub fn first_seen(&self) -> HashMap<SocketId, SystemTime> {
+        let inet = self
+            .rows()
+            .map(|row| (SocketId::Inet(row.key()), row.first_seen));
+        let unix = self
+            .unix_sockets
+            .iter()
+            .map(|row| (SocketId::Unix(row.inode), row.first_seen));
+        let raw = self
+            .raw_sockets
+            .iter()
+            .map(|row| (SocketId::Raw(row.kind, row.inode), row.first_seen));
+        inet.chain(unix).chain(raw).collect()
+    }
+
+    /// Sockets that were already seen by a previous refresh keep their
+    /// original first-seen time instead of the time of the current one.
+    /// A connection that entered TIME_WAIT is found by its addresses, as
+    /// the tracker does.
+    pub fn carry_first_seen(&mut self, first_seen: &HashMap<SocketId, SystemTime>) {
+        let by_address: HashMap<SocketKey, SystemTime> = first_seen
+            .iter()
+            .filter_map(|(id, seen)| match id {
+                SocketId::Inet(key) => Some((without_inode(key), *seen)),
+                _ => None,
+            })
+            .collect();
+        for row in self
+            .tcp_sockets
+            .iter_mut()
+            .chain(self.udp_sockets.iter_mut())
+        {
+            let key = row.key();
+            let seen = match first_seen.get(&SocketId::Inet(key.clone())) {
+                None if row.state == Some(TcpState::TimeWait) => {
+                    by_address.get(&without_inode(&key))
+                }
+                seen => seen,
+            };
+            if let Some(seen) = seen {
+                row.first_seen = *seen;
+            }
+        }
+        for row in &mut self.unix_sockets {
+            if let Some(seen) = first_seen.get(&SocketId::Unix(row.inode)) {
+                row.first_seen = *seen;
+            }
+        }
+        for row in &mut self.raw_sockets {
+            if let Some(seen) = first_seen.get(&SocketId::Raw(row.kind, row.inode)) {
+                row.first_seen = *seen;
+            }
+        }
+    }
+
+    pub fn byte_counters(&self) -> ByteCounters {
+        self.rows()
+            .filter_map(|row| {
+                let tcp_info = row.tcp_info?;
+                Some((row.key(), (tcp_info.bytes_received?, tcp_info.bytes_acked?)))
+            })
+            .collect()
+    }
+
+    /// Turns the growth of the byte counters since `previous`, which was
+    /// taken `elapsed` ago, into per-second rates.
+    pub fn compute_throughput(&mut self, previous: &ByteCounters, elapsed: Duration) {
+        let seconds = elapsed.as_secs_f64();
+        if seconds <= 0.0 {
+            return;
+        }
+        let current = self.byte_counters();
+        for row in self
+            .tcp_sockets
+            .iter_mut()
+            .chain(self.udp_sockets.iter_mut())
+        {
+            let key = row.key();
+            if let (Some(before), Some(now)) = (previous.get(&key), current.get(&key)) {
+                let rate =
+                    |before: u64, now: u64| (now.saturating_sub(before) as f64 / seconds) as u64;
+                row.throughput = Some(Throughput {
+                    recv: rate(before.0, now.0),
+                    send: rate(before.1, now.1),
+                });
+            }
+        }
+    }
+}
+
+/// Serde for `netstat2::TcpState`, which doesn't implement it, as in
+/// `#[serde(with = "tcp_state")]`.
+pub mod tcp_state {
+    use netstat2::TcpState;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    #[derive(Serialize, Deserialize)]
+    #[serde(remote = "TcpState")]
+    enum TcpStateDef {
+        Closed,
+        Listen,
+        SynSent,
+        SynReceived,
+        Established,
+        FinWait1,
+        FinWait2,
+        CloseWait,
+        Closing,
+        LastAck,
+        TimeWait,
+        DeleteTcb,
+        Unknown,
+    }
+
+    pub fn serialize<S: Serializer>(state: &TcpState, serializer: S) -> Result<S::Ok, S::Error> {
+        TcpStateDef::serialize(state, serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<TcpState, D::Error> {
+        TcpStateDef::deserialize(deserializer)
+    }
+
+    pub mod option {
+        use super::TcpStateDef;
+        use netstat2::TcpState;
+        use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+        #[derive(Serialize, Deserialize)]
+        struct Wrapper(#[serde(with = "TcpStateDef")] TcpState);
+
+        pub fn serialize<S: Serializer>(
+            state: &Option<TcpState>,
+            serializer: S,
+        ) -> Result<S::Ok, S::Error> {
+            state.map(Wrapper).serialize(serializer)
+        }
+
+        pub fn deserialize<'de, D: Deserializer<'de>>(
+            deserializer: D,
+        ) -> Result<Option<TcpState>, D::Error> {
+            Ok(Option::<Wrapper>::deserialize(deserializer)?.map(|Wrapper(state)| state))
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Protocol, SocketRow, SocketsContainer, TcpInfo, Throughput};
+    use netstat2::{ProtocolSocketInfo, SocketInfo, TcpSocketInfo, TcpState, UdpSocketInfo};
+    use std::time::{Duration, SystemTime};
+
+    fn tcp(local_port: u16, inode: u32) -> SocketInfo {
+        SocketInfo {
+            protocol_socket_info: ProtocolSocketInfo::Tcp(TcpSocketInfo {
+                local_addr: "127.0.0.1".parse().unwrap(),
+                local_port,
+                remote_addr: "10.0.0.1".parse().unwrap(),
+                remote_port: 443,
+                state: TcpState::Established,
+            }),
+            associated_pids: vec![42],
+            inode,
+        }
+    }
+
+    fn udp(local_port: u16, inode: u32) -> SocketInfo {
+        SocketInfo {
+            protocol_socket_info: ProtocolSocketInfo::Udp(UdpSocketInfo {
+                local_addr: "::1".parse().unwrap(),
+                local_port,
+            }),
+            associated_pids: vec![],
+            inode,
+        }
+    }
+
+    fn container(sockets_info: Vec<SocketInfo>, now: SystemTime) -> SocketsContainer {
+        SocketsContainer::from_rows(
+            sockets_info
+                .into_iter()
+                .map(|si| SocketRow::from_socket_info(si, now))
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn split_by_protocol() {
+        let container = container(
+            vec![tcp(1000, 1), udp(53, 2), tcp(1001, 3)],
+            SystemTime::now(),
+        );
+
+        assert_eq!(container.tcp_sockets.len(), 2);
+        assert_eq!(container.udp_sockets.len(), 1);
+        assert!(container
+            .tcp_sockets
+            .iter()
+            .all(|row| row.protocol == Protocol::Tcp));
+        assert_eq!(container.udp_sockets[0].remote_addr, None);
+    }
+
+    #[test]
+    fn first_seen_survives_refresh() {
+        let before = SystemTime::now();
+        let after = before + Duration::from_secs(5);
+        let previous = container(vec![tcp(1000, 1)], before);
+        let mut current = container(vec![tcp(1000, 1), tcp(1001, 2)], after);
+
+        current.carry_first_seen(&previous.first_seen());
+
+        assert_eq!(current.tcp_sockets[0].first_seen, before);
+        assert_eq!(current.tcp_sockets[1].first_seen, after);
+
+        // the connection loses its inode in TIME_WAIT but not its age
+        let later = after + Duration::from_secs(5);
+        let mut time_wait = container(vec![tcp(1000, 0)], later);
+        time_wait.tcp_sockets[0].state = Some(TcpState::TimeWait);
+        time_wait.carry_first_seen(&current.first_seen());
+        assert_eq!(time_wait.tcp_sockets[0].first_seen, before);
+
+        // a new connection on the same addresses is new
+        let mut reused = container(vec![tcp(1000, 3)], later);
+        reused.carry_first_seen(&current.first_seen());
+        assert_eq!(reused.tcp_sockets[0].first_seen, later);
+    }
+
+    #[test]
+    fn throughput_from_counters() {
+        let now = SystemTime::now();
+        let with_counters = |received: u64, acked: u64| {
+            let mut container = container(vec![tcp(1000, 1), tcp(1001, 2)], now);
+            container.tcp_sockets[0].tcp_info = Some(TcpInfo {
+                bytes_received: Some(received),
+                bytes_acked: Some(acked),
+                ..TcpInfo::default()
+            });
+            container
+        };
+        let previous = with_counters(1000, 500);
+        let mut current = with_counters(3000, 1500);
+
+        current.compute_throughput(&previous.byte_counters(), Duration::from_secs(2));
+
+        assert_eq!(
+            current.tcp_sockets[0].throughput,
+            Some(Throughput {
+                recv: 1000,
+                send: 500
+            })
+        );
+        assert_eq!(current.tcp_sockets[1].throughput, None);
+    }
+}
@@ -0,0 +1,280 @@
+use std::cmp::Ordering;
+use std::fmt;
+
+use crate::sockets::{RawSocketRow, SocketRow, UnixSocketRow};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SortKey {
+    LocalPort,
+    RemoteAddress,
+    RemotePort,
+    State,
+    Pid,
+    ProcessName,
+    /// Top talkers first.
+    Throughput,
+}
+
+impl SortKey {
+    pub fn next(self) -> Self {
+        match self {
+            SortKey::LocalPort => SortKey::RemoteAddress,
+            SortKey::RemoteAddress => SortKey::RemotePort,
+            SortKey::RemotePort => SortKey::State,
+            SortKey::State => SortKey::Pid,
+            SortKey::Pid => SortKey::ProcessName,
+            SortKey::ProcessName => SortKey::Throughput,
+            SortKey::Throughput => SortKey::LocalPort,
+        }
+    }
+
+    fn compare(self, a: &SocketRow, b: &SocketRow) -> Ordering {
+        match self {
+            SortKey::LocalPort => a.local_port.cmp(&b.local_port),
+            SortKey::RemoteAddress => a.remote_addr.cmp(&b.remote_addr),
+            SortKey::RemotePort => a.remote_port.cmp(&b.remote_port),
+            SortKey::State => a
+                .state
+                .map(|state| state as u8)
+                .cmp(&b.state.map(|state| state as u8)),
+            SortKey::Pid => a.pids.iter().min().cmp(&b.pids.iter().min()),
+            SortKey::ProcessName => a.process_name.cmp(&b.process_name),
+            SortKey::Throughput => throughput(b).cmp(&throughput(a)),
+        }
+    }
+
+    /// Unix sockets have no ports or addresses, keys without a counterpart
+    /// sort by path.
+    fn compare_unix(self, a: &UnixSocketRow, b: &UnixSocketRow) -> Ordering {
+        match self {
+            SortKey::State => a.state.cmp(&b.state),
+            SortKey::Pid => a.pids.iter().min().cmp(&b.pids.iter().min()),
+            SortKey::ProcessName => a.process_name.cmp(&b.process_name),
+            _ => a.path.cmp(&b.path),
+        }
+    }
+
+    /// Raw sockets sort by kind for the state and by what they are bound
+    /// to for the keys they have no counterpart of.
+    fn compare_raw(self, a: &RawSocketRow, b: &RawSocketRow) -> Ordering {
+        match self {
+            SortKey::State => a.kind.cmp(&b.kind),
+            SortKey::Pid => a.pids.iter().min().cmp(&b.pids.iter().min()),
+            SortKey::ProcessName => a.process_name.cmp(&b.process_name),
+            _ => (a.kind, a.protocol, &a.endpoint).cmp(&(b.kind, b.protocol, &b.endpoint)),
+        }
+    }
+}
+
+impl fmt::Display for SortKey {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                SortKey::LocalPort => "local port",
+                SortKey::RemoteAddress => "remote address",
+                SortKey::RemotePort => "remote port",
+                SortKey::State => "state",
+                SortKey::Pid => "pid",
+                SortKey::ProcessName => "process",
+                SortKey::Throughput => "throughput",
+            }
+        )
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Sorting {
+    pub key: SortKey,
+    pub reversed: bool,
+}
+
+impl Sorting {
+    pub fn new() -> Self {
+        Sorting {
+            key: SortKey::LocalPort,
+            reversed: false,
+        }
+    }
+
+    /// Sorts by the active key; ties are broken by the socket identity so
+    /// rows with equal keys keep their relative order between refreshes.
+    pub fn apply(&self, rows: &mut [SocketRow]) {
+        rows.sort_by(|a, b| self.compare(a, b));
+    }
+
+    /// The order `apply` puts two sockets in.
+    pub fn compare(&self, a: &SocketRow, b: &SocketRow) -> Ordering {
+        let ordering = self.key.compare(a, b).then_with(|| tie_break(a, b));
+        if self.reversed {
+            ordering.reverse()
+        } else {
+            ordering
+        }
+    }
+
+    pub fn apply_raw(&self, rows: &mut [RawSocketRow]) {
+        rows.sort_by(|a, b| {
+            let ordering = self
+                .key
+                .compare_raw(a, b)
+                .then_with(|| (a.kind, a.inode).cmp(&(b.kind, b.inode)));
+            if self.reversed {
+                ordering.reverse()
+            } else {
+                ordering
+            }
+        });
+    }
+
+    pub fn apply_unix(&self, rows: &mut [UnixSocketRow]) {
+        rows.sort_by(|a, b| {
+            let ordering = self
+                .key
+                .compare_unix(a, b)
+                .then_with(|| (&a.path, a.inode).cmp(&(&b.path, b.inode)));
+            if self.reversed {
+                ordering.reverse()
+            } else {
+                ordering
+            }
+        });
+    }
+}
+
+impl fmt::Display for Sorting {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} {}", self.key, if self.reversed { "▼" } else { "▲" })
+    }
+}
+
+fn throughput(row: &SocketRow) -> u64 {
+    row.throughput
+        .map(|throughput| throughput.total())
+        .unwrap_or_default()
+}
+
+fn tie_break(a: &SocketRow, b: &SocketRow) -> Ordering {
+    (
+        a.local_addr,
+        a.local_port,
+        a.remote_addr,
+        a.remote_port,
+        a.inode,
+    )
+        .cmp(&(
+            b.local_addr,
+            b.local_port,
+            b.remote_addr,
+            b.remote_port,
+            b.inode,
+        ))
+}
+
+#[cfg(test)]
+mod test {
+    use super::{SortKey, Sorting};
+    use crate::sockets::{SocketRow, Throughput, UnixSocketRow, UnixState, UnixType};
+    use netstat2::TcpState;
+    use std::time::SystemTime;
+
+    fn row(local_port: u16, state: TcpState, pid: u32) -> SocketRow {
+        let local = format!("127.0.0.1:{}", local_port);
+        SocketRow {
+            pids: vec![pid],
+            ..SocketRow::test_tcp(&local, "10.0.0.1:443", state)
+        }
+    }
+
+    fn ports(rows: &[SocketRow]) -> Vec<u16> {
+        rows.iter().map(|row| row.local_port).collect()
+    }
+
+    #[test]
+    fn sort_and_reverse() {
+        let mut rows = vec![
+            row(8080, TcpState::Listen, 3),
+            row(22, TcpState::Established, 1),
+            row(443, TcpState::Established, 2),
+        ];
+        let mut sorting = Sorting::new();
+
+        sorting.apply(&mut rows);
+        assert_eq!(ports(&rows), vec![22, 443, 8080]);
+
+        sorting.reversed = true;
+        sorting.apply(&mut rows);
+        assert_eq!(ports(&rows), vec![8080, 443, 22]);
+    }
+
+    #[test]
+    fn ties_are_broken_by_identity() {
+        let mut rows = vec![
+            row(443, TcpState::Established, 2),
+            row(8080, TcpState::Listen, 3),
+            row(22, TcpState::Established, 1),
+        ];
+        let sorting = Sorting {
+            key: SortKey::State,
+            reversed: false,
+        };
+
+        sorting.apply(&mut rows);
+        assert_eq!(ports(&rows), vec![8080, 22, 443]);
+    }
+
+    #[test]
+    fn top_talkers_first() {
+        let mut rows = vec![
+            row(22, TcpState::Established, 1),
+            row(443, TcpState::Established, 2),
+            row(8080, TcpState::Listen, 3),
+        ];
+        rows[0].throughput = Some(Throughput { recv: 10, send: 0 });
+        rows[1].throughput = Some(Throughput {
+            recv: 500,
+            send: 1000,
+        });
+        let sorting = Sorting {
+            key: SortKey::Throughput,
+            reversed: false,
+        };
+
+        sorting.apply(&mut rows);
+        assert_eq!(ports(&rows), vec![443, 22, 8080]);
+    }
+
+    fn unix_row(path: Option<&str>, state: UnixState, inode: u32) -> UnixSocketRow {
+        UnixSocketRow {
+            socket_type: UnixType::Stream,
+            path: path.map(str::to_owned),
+            state,
+            inode,
+            peer_inode: None,
+            pids: vec![1],
+            process_name: None,
+            first_seen: SystemTime::now(),
+        }
+    }
+
+    #[test]
+    fn unix_sockets_without_a_matching_key_sort_by_path() {
+        let mut rows = vec![
+            unix_row(Some("/run/docker.sock"), UnixState::Listen, 3),
+            unix_row(None, UnixState::Connected, 2),
+            unix_row(Some("/run/dbus"), UnixState::Connected, 1),
+        ];
+        let inodes = |rows: &[UnixSocketRow]| rows.iter().map(|row| row.inode).collect::<Vec<_>>();
+
+        Sorting::new().apply_unix(&mut rows);
+        assert_eq!(inodes(&rows), vec![2, 1, 3]);
+
+        let by_state = Sorting {
+            key: SortKey::State,
+            reversed: false,
+        };
+        by_state.apply_unix(&mut rows);
+        assert_eq!(inodes(&rows), vec![3, 2, 1]);
+    }
+}
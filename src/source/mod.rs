@@ -0,0 +1,275 @@
+//! Backends that list the sockets of the system.
+
+mod netstat;
+mod procfs;
+mod sock_diag;
+
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::net::SocketAddr;
+use std::str::FromStr;
+use std::time::SystemTime;
+
+use netstat2::TcpState;
+
+use crate::errors::ConnectionToolsError;
+use crate::sockets::{
+    Family, Protocol, Queues, RawSocketRow, SocketMemory, SocketRow, TcpInfo, UnixSocketRow,
+    UnixState, UnixType,
+};
+
+pub use self::netstat::NetstatSource;
+pub use self::procfs::ProcfsSource;
+pub use self::sock_diag::{DestroyListener, SockDiagSource};
+
+pub trait SocketSource: Send {
+    /// Lists all TCP and UDP sockets; rows get `now` as their first-seen time.
+    /// `owners` is the result of `socket_owners` for sources that can't tell
+    /// the owning processes themselves.
+    fn collect(
+        &mut self,
+        now: SystemTime,
+        owners: &SocketOwners,
+    ) -> Result<Vec<SocketRow>, ConnectionToolsError>;
+
+    /// Whether the TCP and UDP rows get their owners without `owners`, which
+    /// then only serves the Unix and raw sockets and may be a little old.
+    fn finds_owners(&self) -> bool {
+        false
+    }
+}
+
+/// Pids holding each socket inode open.
+pub type SocketOwners = HashMap<u32, Vec<u32>>;
+
+/// Lists Unix sockets over sock_diag, which knows their peers, or from
+/// `/proc/net/unix` when netlink is not available.
+pub fn collect_unix(
+    now: SystemTime,
+    owners: &SocketOwners,
+) -> Result<Vec<UnixSocketRow>, ConnectionToolsError> {
+    let mut rows = sock_diag::collect_unix(now).or_else(|_| procfs::collect_unix(now))?;
+    for row in &mut rows {
+        row.pids = owners.get(&row.inode).cloned().unwrap_or_default();
+    }
+    Ok(rows)
+}
+
+/// Lists raw IP, packet and netlink sockets. Only `/proc/net` knows all
+/// three kinds, whatever the chosen source.
+pub fn collect_raw(
+    now: SystemTime,
+    owners: &SocketOwners,
+) -> Result<Vec<RawSocketRow>, ConnectionToolsError> {
+    let mut rows = procfs::collect_raw(now)?;
+    for row in &mut rows {
+        row.pids = owners.get(&row.inode).cloned().unwrap_or_default();
+    }
+    Ok(rows)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SourceKind {
+    #[default]
+    Netstat2,
+    Procfs,
+    SockDiag,
+}
+
+impl SourceKind {
+    pub const ALL: [SourceKind; 3] = [
+        SourceKind::Netstat2,
+        SourceKind::Procfs,
+        SourceKind::SockDiag,
+    ];
+
+    pub fn create(self) -> Box<dyn SocketSource> {
+        match self {
+            SourceKind::Netstat2 => Box::new(NetstatSource),
+            SourceKind::Procfs => Box::new(ProcfsSource),
+            SourceKind::SockDiag => Box::new(SockDiagSource),
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            SourceKind::Netstat2 => "netstat2",
+            SourceKind::Procfs => "procfs",
+            SourceKind::SockDiag => "sock_diag",
+        }
+    }
+}
+
+impl fmt::Display for SourceKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+impl FromStr for SourceKind {
+    type Err = ConnectionToolsError;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        SourceKind::ALL
+            .iter()
+            .copied()
+            .find(|kind| kind.name() == name)
+            .ok_or_else(|| ConnectionToolsError::UnknownSource {
+                name: name.to_owned(),
+            })
+    }
+}
+
+/// Fields the kernel reports for every inet socket, whatever the interface.
+struct KernelSocket {
+    protocol: Protocol,
+    local: SocketAddr,
+    remote: SocketAddr,
+    state: u8,
+    inode: u32,
+    uid: u32,
+    queues: Option<Queues>,
+    tcp_info: Option<TcpInfo>,
+    memory: Option<SocketMemory>,
+}
+
+impl KernelSocket {
+    fn into_row(self, owners: &SocketOwners, now: SystemTime) -> SocketRow {
+        let (remote_addr, remote_port, state) = match self.protocol {
+            Protocol::Tcp => (
+                Some(self.remote.ip()),
+                Some(self.remote.port()),
+                Some(tcp_state_from_kernel(self.state)),
+            ),
+            Protocol::Udp => (None, None, None),
+        };
+        SocketRow {
+            protocol: self.protocol,
+            family: Family::of(&self.local.ip()),
+            local_addr: self.local.ip(),
+            local_port: self.local.port(),
+            remote_addr,
+            remote_port,
+            state,
+            pids: owners.get(&self.inode).cloned().unwrap_or_default(),
+            inode: self.inode,
+            uid: Some(self.uid),
+            process_name: None,
+            first_seen: now,
+            queues: self.queues,
+            tcp_info: self.tcp_info,
+            memory: self.memory,
+            throughput: None,
+        }
+    }
+}
+
+/// Numbering of `include/net/tcp_states.h`.
+fn tcp_state_from_kernel(state: u8) -> TcpState {
+    match state {
+        1 => TcpState::Established,
+        2 => TcpState::SynSent,
+        3 | 12 => TcpState::SynReceived,
+        4 => TcpState::FinWait1,
+        5 => TcpState::FinWait2,
+        6 => TcpState::TimeWait,
+        7 => TcpState::Closed,
+        8 => TcpState::CloseWait,
+        9 => TcpState::LastAck,
+        10 => TcpState::Listen,
+        11 => TcpState::Closing,
+        _ => TcpState::Unknown,
+    }
+}
+
+/// `SOCK_STREAM`, `SOCK_DGRAM` and `SOCK_SEQPACKET`.
+fn unix_type(socket_type: u32) -> Option<UnixType> {
+    match socket_type as i32 {
+        libc::SOCK_STREAM => Some(UnixType::Stream),
+        libc::SOCK_DGRAM => Some(UnixType::Dgram),
+        libc::SOCK_SEQPACKET => Some(UnixType::SeqPacket),
+        _ => None,
+    }
+}
+
+fn unix_row(
+    socket_type: UnixType,
+    path: Option<String>,
+    state: UnixState,
+    inode: u32,
+    peer_inode: Option<u32>,
+    now: SystemTime,
+) -> UnixSocketRow {
+    UnixSocketRow {
+        socket_type,
+        path,
+        state,
+        inode,
+        peer_inode,
+        pids: Vec::new(),
+        process_name: None,
+        first_seen: now,
+    }
+}
+
+/// Maps socket inodes to the pids holding them open, found by scanning
+/// `/proc/<pid>/fd`. Processes of other users are skipped silently.
+pub fn socket_owners() -> SocketOwners {
+    let mut owners = SocketOwners::new();
+    let proc_entries = match fs::read_dir("/proc") {
+        Ok(proc_entries) => proc_entries,
+        Err(_) => return owners,
+    };
+
+    for proc_entry in proc_entries.flatten() {
+        let pid = match proc_entry.file_name().to_str().map(u32::from_str) {
+            Some(Ok(pid)) => pid,
+            _ => continue,
+        };
+        let fd_entries = match fs::read_dir(proc_entry.path().join("fd")) {
+            Ok(fd_entries) => fd_entries,
+            Err(_) => continue,
+        };
+        for fd_entry in fd_entries.flatten() {
+            let inode = fs::read_link(fd_entry.path())
+                .ok()
+                .and_then(|link| link.to_str().and_then(parse_socket_link));
+            if let Some(inode) = inode {
+                let pids = owners.entry(inode).or_default();
+                if !pids.contains(&pid) {
+                    pids.push(pid);
+                }
+            }
+        }
+    }
+    owners
+}
+
+/// Socket file descriptors link to `socket:[<inode>]`.
+fn parse_socket_link(link: &str) -> Option<u32> {
+    link.strip_prefix("socket:[")?
+        .strip_suffix(']')?
+        .parse()
+        .ok()
+}
+
+#[cfg(test)]
+mod test {
+    use super::{parse_socket_link, SourceKind};
+
+    #[test]
+    fn socket_links() {
+        assert_eq!(parse_socket_link("socket:[12345]"), Some(12345));
+        assert_eq!(parse_socket_link("pipe:[12345]"), None);
+        assert_eq!(parse_socket_link("/dev/null"), None);
+    }
+
+    #[test]
+    fn source_names_round_trip() {
+        for kind in SourceKind::ALL.iter() {
+            assert_eq!(kind.to_string().parse::<SourceKind>().unwrap(), *kind);
+        }
+        assert!("pcap".parse::<SourceKind>().is_err());
+    }
+}
@@ -0,0 +1,36 @@
+use std::time::SystemTime;
+
+use netstat2::{get_sockets_info, AddressFamilyFlags, ProtocolFlags};
+
+use super::{SocketOwners, SocketSource};
+use crate::errors::ConnectionToolsError;
+use crate::sockets::SocketRow;
+
+/// Sockets as reported by the `netstat2` crate, which finds the owning
+/// processes on its own.
+pub struct NetstatSource;
+
+impl SocketSource for NetstatSource {
+    fn collect(
+        &mut self,
+        now: SystemTime,
+        _owners: &SocketOwners,
+    ) -> Result<Vec<SocketRow>, ConnectionToolsError> {
+        let af_flags = AddressFamilyFlags::IPV4 | AddressFamilyFlags::IPV6;
+        let proto_flags = ProtocolFlags::TCP | ProtocolFlags::UDP;
+        let sockets_info = get_sockets_info(af_flags, proto_flags).map_err(|err| {
+            ConnectionToolsError::FailToGetSocketsInfo {
+                message: format!("{}", err),
+            }
+        })?;
+
+        Ok(sockets_info
+            .into_iter()
+            .map(|si| SocketRow::from_socket_info(si, now))
+            .collect())
+    }
+
+    fn finds_owners(&self) -> bool {
+        true
+    }
+}
@@ -0,0 +1,420 @@
+use std::ffi::CStr;
+use std::fs;
+use std::io;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::time::SystemTime;
+
+use super::{unix_row, unix_type, KernelSocket, SocketOwners, SocketSource};
+use crate::errors::ConnectionToolsError;
+use crate::sockets::{
+    Protocol, Queues, RawKind, RawSocketRow, SocketRow, UnixSocketRow, UnixState,
+};
+
+const TABLES: [(&str, Protocol); 4] = [
+    ("/proc/net/tcp", Protocol::Tcp),
+    ("/proc/net/tcp6", Protocol::Tcp),
+    ("/proc/net/udp", Protocol::Udp),
+    ("/proc/net/udp6", Protocol::Udp),
+];
+
+/// Sockets read straight from the `/proc/net/{tcp,tcp6,udp,udp6}` tables.
+pub struct ProcfsSource;
+
+impl SocketSource for ProcfsSource {
+    fn collect(
+        &mut self,
+        now: SystemTime,
+        owners: &SocketOwners,
+    ) -> Result<Vec<SocketRow>, ConnectionToolsError> {
+        let mut rows = Vec::new();
+        for (path, protocol) in TABLES.iter() {
+            let table = match fs::read_to_string(path) {
+                Ok(table) => table,
+                // a kernel built without IPv6 has no tcp6/udp6 tables
+                Err(_) if path.ends_with('6') => continue,
+                Err(err) => {
+                    return Err(ConnectionToolsError::FailToGetSocketsInfo {
+                        message: format!("{}: {}", path, err),
+                    })
+                }
+            };
+            rows.extend(
+                parse_table(&table, *protocol)?
+                    .into_iter()
+                    .map(|socket| socket.into_row(owners, now)),
+            );
+        }
+        Ok(rows)
+    }
+}
+
+/// `/proc/net/unix` has no peers, see `sock_diag::collect_unix` for those.
+pub fn collect_unix(now: SystemTime) -> Result<Vec<UnixSocketRow>, ConnectionToolsError> {
+    let table = fs::read_to_string("/proc/net/unix").map_err(|err| {
+        ConnectionToolsError::FailToGetSocketsInfo {
+            message: format!("/proc/net/unix: {}", err),
+        }
+    })?;
+    Ok(parse_unix_table(&table, now))
+}
+
+/// Raw IP sockets come from `raw` and `raw6`, which share the layout of the
+/// TCP table. Tables of modules that are not loaded are skipped.
+pub fn collect_raw(now: SystemTime) -> Result<Vec<RawSocketRow>, ConnectionToolsError> {
+    let mut rows = Vec::new();
+    for path in &["/proc/net/raw", "/proc/net/raw6"] {
+        if let Some(table) = read_optional_table(path)? {
+            rows.extend(parse_raw_table(&table, now));
+        }
+    }
+    if let Some(table) = read_optional_table("/proc/net/packet")? {
+        rows.extend(parse_packet_table(&table, now));
+    }
+    if let Some(table) = read_optional_table("/proc/net/netlink")? {
+        rows.extend(parse_netlink_table(&table, now));
+    }
+    Ok(rows)
+}
+
+fn read_optional_table(path: &str) -> Result<Option<String>, ConnectionToolsError> {
+    match fs::read_to_string(path) {
+        Ok(table) => Ok(Some(table)),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(err) => Err(ConnectionToolsError::FailToGetSocketsInfo {
+            message: format!("{}: {}", path, err),
+        }),
+    }
+}
+
+/// The "port" of a raw socket is its IP protocol.
+fn parse_raw_table(table: &str, now: SystemTime) -> Vec<RawSocketRow> {
+    table
+        .lines()
+        .skip(1)
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            if fields.len() < 10 {
+                return None;
+            }
+            let local = parse_socket_addr(fields[1])?;
+            Some(raw_row(
+                RawKind::Ip,
+                local.port(),
+                local.ip().to_string(),
+                Some(fields[7].parse().ok()?),
+                fields[9].parse().ok()?,
+                now,
+            ))
+        })
+        .collect()
+}
+
+/// `sk RefCnt Type Proto Iface R Rmem User Inode`, the protocol in hex.
+fn parse_packet_table(table: &str, now: SystemTime) -> Vec<RawSocketRow> {
+    table
+        .lines()
+        .skip(1)
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            if fields.len() < 9 {
+                return None;
+            }
+            Some(raw_row(
+                RawKind::Packet,
+                u16::from_str_radix(fields[3], 16).ok()?,
+                interface_name(fields[4].parse().ok()?),
+                Some(fields[7].parse().ok()?),
+                fields[8].parse().ok()?,
+                now,
+            ))
+        })
+        .collect()
+}
+
+/// `sk Eth Pid Groups Rmem Wmem Dump Locks Drops Inode`, the groups in hex.
+fn parse_netlink_table(table: &str, now: SystemTime) -> Vec<RawSocketRow> {
+    table
+        .lines()
+        .skip(1)
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            if fields.len() < 10 {
+                return None;
+            }
+            let port_id: u32 = fields[2].parse().ok()?;
+            let groups = u32::from_str_radix(fields[3], 16).ok()?;
+            let endpoint = if groups == 0 {
+                format!("port {}", port_id)
+            } else {
+                format!("port {} groups {:#x}", port_id, groups)
+            };
+            Some(raw_row(
+                RawKind::Netlink,
+                fields[1].parse().ok()?,
+                endpoint,
+                None,
+                fields[9].parse().ok()?,
+                now,
+            ))
+        })
+        .collect()
+}
+
+fn raw_row(
+    kind: RawKind,
+    protocol: u16,
+    endpoint: String,
+    uid: Option<u32>,
+    inode: u32,
+    now: SystemTime,
+) -> RawSocketRow {
+    RawSocketRow {
+        kind,
+        protocol,
+        endpoint,
+        uid,
+        inode,
+        pids: Vec::new(),
+        process_name: None,
+        first_seen: now,
+    }
+}
+
+/// Packet sockets bound to no interface capture on all of them.
+fn interface_name(index: u32) -> String {
+    if index == 0 {
+        return "any".to_owned();
+    }
+    let mut name = [0 as libc::c_char; libc::IF_NAMESIZE];
+    // SAFETY: the buffer is IF_NAMESIZE long, as if_indextoname requires
+    let found = unsafe { !libc::if_indextoname(index, name.as_mut_ptr()).is_null() };
+    if found {
+        // SAFETY: if_indextoname wrote a nul terminated name
+        unsafe { CStr::from_ptr(name.as_ptr()) }
+            .to_string_lossy()
+            .into_owned()
+    } else {
+        format!("if{}", index)
+    }
+}
+
+/// `Num RefCount Protocol Flags Type St Inode Path`, all hex but the inode.
+fn parse_unix_table(table: &str, now: SystemTime) -> Vec<UnixSocketRow> {
+    table
+        .lines()
+        .skip(1)
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            if fields.len() < 7 {
+                return None;
+            }
+            let flags = u32::from_str_radix(fields[3], 16).ok()?;
+            let socket_type = unix_type(u32::from_str_radix(fields[4], 16).ok()?)?;
+            // __SO_ACCEPTCON marks listening sockets
+            let state = if flags & 0x0001_0000 != 0 {
+                UnixState::Listen
+            } else {
+                match fields[5] {
+                    "02" => UnixState::Connecting,
+                    "03" => UnixState::Connected,
+                    "04" => UnixState::Disconnecting,
+                    _ => UnixState::Unconnected,
+                }
+            };
+            let inode = fields[6].parse().ok()?;
+            let path = if fields.len() > 7 {
+                Some(fields[7..].join(" "))
+            } else {
+                None
+            };
+            Some(unix_row(socket_type, path, state, inode, None, now))
+        })
+        .collect()
+}
+
+fn parse_table(table: &str, protocol: Protocol) -> Result<Vec<KernelSocket>, ConnectionToolsError> {
+    table
+        .lines()
+        .skip(1)
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| {
+            parse_line(line, protocol).ok_or_else(|| ConnectionToolsError::FailToGetSocketsInfo {
+                message: format!("unexpected /proc/net line '{}'", line.trim()),
+            })
+        })
+        .collect()
+}
+
+/// `sl local_address rem_address st tx_queue:rx_queue tr:tm->when retrnsmt uid timeout inode ...`
+fn parse_line(line: &str, protocol: Protocol) -> Option<KernelSocket> {
+    let fields: Vec<&str> = line.split_whitespace().collect();
+    if fields.len() < 10 {
+        return None;
+    }
+    Some(KernelSocket {
+        protocol,
+        local: parse_socket_addr(fields[1])?,
+        remote: parse_socket_addr(fields[2])?,
+        state: u8::from_str_radix(fields[3], 16).ok()?,
+        uid: fields[7].parse().ok()?,
+        inode: fields[9].parse().ok()?,
+        queues: Some(parse_queues(fields[4])?),
+        tcp_info: None,
+        memory: None,
+    })
+}
+
+/// `tx_queue:rx_queue`, both in hex.
+fn parse_queues(value: &str) -> Option<Queues> {
+    let mut parts = value.split(':');
+    let send = u32::from_str_radix(parts.next()?, 16).ok()?;
+    let recv = u32::from_str_radix(parts.next()?, 16).ok()?;
+    Some(Queues { recv, send })
+}
+
+/// Addresses are printed as hex words in host byte order, ports in hex.
+fn parse_socket_addr(value: &str) -> Option<SocketAddr> {
+    let mut parts = value.split(':');
+    let addr = parts.next()?;
+    let port = u16::from_str_radix(parts.next()?, 16).ok()?;
+
+    let ip = match addr.len() {
+        8 => IpAddr::V4(Ipv4Addr::from(parse_word(addr)?)),
+        32 => {
+            let mut octets = [0u8; 16];
+            for (index, chunk) in octets.chunks_mut(4).enumerate() {
+                chunk.copy_from_slice(&parse_word(&addr[index * 8..index * 8 + 8])?);
+            }
+            IpAddr::V6(Ipv6Addr::from(octets))
+        }
+        _ => return None,
+    };
+    Some(SocketAddr::new(ip, port))
+}
+
+fn parse_word(hex: &str) -> Option<[u8; 4]> {
+    u32::from_str_radix(hex, 16).ok().map(u32::to_ne_bytes)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{
+        parse_netlink_table, parse_packet_table, parse_raw_table, parse_table, parse_unix_table,
+    };
+    use crate::sockets::{Protocol, Queues, RawKind, UnixState, UnixType};
+    use std::net::SocketAddr;
+    use std::time::SystemTime;
+
+    fn hex_v4(addr: [u8; 4], port: u16) -> String {
+        format!("{:08X}:{:04X}", u32::from_ne_bytes(addr), port)
+    }
+
+    #[test]
+    fn parse_tcp_table() {
+        let table = format!(
+            "  sl  local_address rem_address   st tx_queue rx_queue tr tm->when retrnsmt   uid  timeout inode\n\
+             \x20  0: {} {} 0A 00000000:00000000 00:00000000 00000000   109        0 23456 1 0000000000000000 100 0 0 10 0\n\
+             \x20  1: {} {} 01 00000010:00000200 02:000A7D2B 00000000  1000        0 34567 2 0000000000000000 20 4 30 10 -1\n",
+            hex_v4([127, 0, 0, 1], 5432),
+            hex_v4([0, 0, 0, 0], 0),
+            hex_v4([10, 0, 0, 2], 41234),
+            hex_v4([10, 0, 0, 9], 443),
+        );
+
+        let sockets = parse_table(&table, Protocol::Tcp).unwrap();
+
+        assert_eq!(sockets.len(), 2);
+        assert_eq!(
+            sockets[0].local,
+            "127.0.0.1:5432".parse::<SocketAddr>().unwrap()
+        );
+        assert_eq!(sockets[0].state, 0x0A);
+        assert_eq!(sockets[0].uid, 109);
+        assert_eq!(sockets[0].inode, 23456);
+        assert_eq!(
+            sockets[1].queues,
+            Some(Queues {
+                recv: 0x200,
+                send: 0x10
+            })
+        );
+        assert_eq!(
+            sockets[1].remote,
+            "10.0.0.9:443".parse::<SocketAddr>().unwrap()
+        );
+    }
+
+    #[test]
+    fn parse_ipv6_address() {
+        let loopback = [0u32, 0, 0, u32::from_ne_bytes([0, 0, 0, 1])];
+        let addr = loopback
+            .iter()
+            .map(|word| format!("{:08X}", word))
+            .collect::<String>();
+        let table = format!(
+            "header\n   0: {}:0035 {}:0000 07 00000000:00000000 00:00000000 00000000   101        0 4567 2 0000000000000000 0\n",
+            addr, "00000000000000000000000000000000"
+        );
+
+        let sockets = parse_table(&table, Protocol::Udp).unwrap();
+
+        assert_eq!(sockets[0].local, "[::1]:53".parse::<SocketAddr>().unwrap());
+    }
+
+    #[test]
+    fn reject_garbage() {
+        assert!(parse_table("header\n   0: nonsense\n", Protocol::Tcp).is_err());
+    }
+
+    #[test]
+    fn parse_unix() {
+        let table = "Num       RefCount Protocol Flags    Type St Inode Path\n\
+                     0000000000000000: 00000002 00000000 00010000 0001 01 23456 /run/docker.sock\n\
+                     0000000000000000: 00000003 00000000 00000000 0001 03 23457\n\
+                     0000000000000000: 00000002 00000000 00000000 0002 01 23458 @/tmp/.X11-unix/X0\n";
+
+        let rows = parse_unix_table(table, SystemTime::now());
+
+        assert_eq!(rows.len(), 3);
+        assert_eq!(rows[0].state, UnixState::Listen);
+        assert_eq!(rows[0].path.as_deref(), Some("/run/docker.sock"));
+        assert_eq!(rows[1].state, UnixState::Connected);
+        assert_eq!(rows[1].path, None);
+        assert_eq!(rows[2].socket_type, UnixType::Dgram);
+        assert_eq!(rows[2].inode, 23458);
+    }
+
+    #[test]
+    fn parse_raw_packet_and_netlink() {
+        let now = SystemTime::now();
+        let raw = format!(
+            "  sl  local_address rem_address   st tx_queue rx_queue tr tm->when retrnsmt   uid  timeout inode ref pointer drops\n\
+             \x20  1: {} {} 07 00000000:00000000 00:00000000 00000000     0        0 40123 2 0000000000000000 0\n",
+            hex_v4([0, 0, 0, 0], 1),
+            hex_v4([0, 0, 0, 0], 0),
+        );
+        let packet = "sk               RefCnt Type Proto  Iface R Rmem   User   Inode\n\
+                      0000000000000000 3      3    0003   0     1 0      0      40124\n";
+        let netlink = "sk               Eth Pid        Groups   Rmem     Wmem     Dump  Locks    Drops    Inode\n\
+                       0000000000000000 0   812        00000551 0        0        0     2        0        40125\n\
+                       0000000000000000 4   0          00000000 0        0        0     2        0        4\n";
+
+        let raw = parse_raw_table(&raw, now);
+        assert_eq!(raw[0].kind, RawKind::Ip);
+        assert_eq!(raw[0].protocol_name(), "icmp");
+        assert_eq!(raw[0].endpoint, "0.0.0.0");
+        assert_eq!((raw[0].uid, raw[0].inode), (Some(0), 40123));
+
+        let packet = parse_packet_table(packet, now);
+        assert_eq!(packet[0].protocol_name(), "all");
+        assert_eq!(packet[0].endpoint, "any");
+        assert_eq!(packet[0].inode, 40124);
+
+        let netlink = parse_netlink_table(netlink, now);
+        assert_eq!(netlink.len(), 2);
+        assert_eq!(netlink[0].protocol_name(), "route");
+        assert_eq!(netlink[0].endpoint, "port 812 groups 0x551");
+        assert_eq!(netlink[1].protocol_name(), "sock_diag");
+        assert_eq!(netlink[1].uid, None);
+    }
+}
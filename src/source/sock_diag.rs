@@ -0,0 +1,684 @@
+use std::convert::TryInto;
+use std::io;
+use std::mem;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::time::SystemTime;
+
+use super::{unix_row, unix_type, KernelSocket, SocketOwners, SocketSource};
+use crate::errors::ConnectionToolsError;
+use crate::sockets::{
+    Protocol, Queues, SocketMemory, SocketRow, TcpInfo, UnixSocketRow, UnixState,
+};
+
+/// `SOCK_DIAG_BY_FAMILY` from `linux/sock_diag.h`.
+const SOCK_DIAG_BY_FAMILY: u16 = 20;
+const NLMSG_HEADER_LEN: usize = 16;
+/// `struct inet_diag_req_v2`.
+const REQUEST_LEN: usize = 56;
+/// `struct inet_diag_msg`.
+const DIAG_MSG_LEN: usize = 72;
+/// `struct unix_diag_req`.
+const UNIX_REQUEST_LEN: usize = 24;
+/// `struct unix_diag_msg`.
+const UNIX_DIAG_MSG_LEN: usize = 16;
+const ALL_STATES: u32 = !0;
+/// `INET_DIAG_INFO` carries a `struct tcp_info`.
+const INET_DIAG_INFO: u16 = 2;
+/// `INET_DIAG_SKMEMINFO` carries the `SK_MEMINFO_*` array.
+const INET_DIAG_SKMEMINFO: u16 = 7;
+/// `INET_DIAG_PROTOCOL`, only sent with destroy notifications.
+const INET_DIAG_PROTOCOL: u16 = 10;
+/// `SKNLGRP_INET_TCP_DESTROY`, `SKNLGRP_INET_UDP_DESTROY`,
+/// `SKNLGRP_INET6_TCP_DESTROY` and `SKNLGRP_INET6_UDP_DESTROY`.
+const DESTROY_GROUPS: [u32; 4] = [1, 2, 3, 4];
+/// `UDIAG_SHOW_NAME` and `UDIAG_SHOW_PEER`.
+const UNIX_SHOW: u32 = 0x01 | 0x04;
+const UNIX_DIAG_NAME: u16 = 0;
+const UNIX_DIAG_PEER: u16 = 2;
+const RTATTR_HEADER_LEN: usize = 4;
+const RECV_BUFFER_LEN: usize = 32 * 1024;
+
+/// Sockets dumped by the kernel over a `NETLINK_SOCK_DIAG` socket, the
+/// interface `ss` uses.
+pub struct SockDiagSource;
+
+impl SocketSource for SockDiagSource {
+    fn collect(
+        &mut self,
+        now: SystemTime,
+        owners: &SocketOwners,
+    ) -> Result<Vec<SocketRow>, ConnectionToolsError> {
+        let netlink = NetlinkSocket::open().map_err(sock_diag_error)?;
+        let mut sockets = Vec::new();
+        let requests = [
+            (libc::AF_INET, Protocol::Tcp),
+            (libc::AF_INET6, Protocol::Tcp),
+            (libc::AF_INET, Protocol::Udp),
+            (libc::AF_INET6, Protocol::Udp),
+        ];
+        for (seq, (family, protocol)) in requests.iter().enumerate() {
+            let request = inet_request(*family as u8, *protocol, seq as u32 + 1);
+            netlink.dump(&request, |payload| {
+                if let Some(socket) = parse_diag_msg(payload, *protocol) {
+                    sockets.push(socket);
+                }
+            })?;
+        }
+
+        Ok(sockets
+            .into_iter()
+            .map(|socket| socket.into_row(owners, now))
+            .collect())
+    }
+}
+
+pub fn collect_unix(now: SystemTime) -> Result<Vec<UnixSocketRow>, ConnectionToolsError> {
+    let netlink = NetlinkSocket::open().map_err(sock_diag_error)?;
+    let mut rows = Vec::new();
+    netlink.dump(&unix_request(1), |payload| {
+        if let Some(row) = parse_unix_diag_msg(payload, now) {
+            rows.push(row);
+        }
+    })?;
+    Ok(rows)
+}
+
+/// TCP and UDP sockets reported by the kernel as they are destroyed, with
+/// their final `tcp_info`, however short they lived.
+pub struct DestroyListener {
+    netlink: NetlinkSocket,
+}
+
+impl DestroyListener {
+    /// Joining the destroy groups needs `CAP_NET_ADMIN`.
+    pub fn open() -> Result<Self, ConnectionToolsError> {
+        let watch_error = |err: io::Error| ConnectionToolsError::WatchDestroyed {
+            message: err.to_string(),
+        };
+        let netlink = NetlinkSocket::open().map_err(watch_error)?;
+        let groups = DESTROY_GROUPS
+            .iter()
+            .fold(0, |groups, group| groups | 1 << (group - 1));
+        netlink.bind(groups).map_err(watch_error)?;
+        Ok(DestroyListener { netlink })
+    }
+
+    /// Blocks until the kernel reports destroyed sockets; they get `now` as
+    /// their first-seen time.
+    pub fn next(&self, owners: &SocketOwners) -> Result<Vec<SocketRow>, ConnectionToolsError> {
+        let mut buffer = vec![0u8; RECV_BUFFER_LEN];
+        loop {
+            let len = match self.netlink.recv(&mut buffer) {
+                Ok(len) => len,
+                // the kernel dropped notifications we were too slow to read
+                Err(err) if err.raw_os_error() == Some(libc::ENOBUFS) => continue,
+                Err(err) => return Err(sock_diag_error(err)),
+            };
+            let now = SystemTime::now();
+            let mut rows = Vec::new();
+            parse_messages(&buffer[..len], &mut |payload| {
+                if let Some(socket) = parse_destroyed(payload) {
+                    rows.push(socket.into_row(owners, now));
+                }
+            })?;
+            if !rows.is_empty() {
+                return Ok(rows);
+            }
+        }
+    }
+}
+
+fn sock_diag_error(err: io::Error) -> ConnectionToolsError {
+    ConnectionToolsError::FailToGetSocketsInfo {
+        message: format!("sock_diag: {}", err),
+    }
+}
+
+struct NetlinkSocket {
+    fd: libc::c_int,
+}
+
+impl NetlinkSocket {
+    fn open() -> io::Result<Self> {
+        // SAFETY: socket takes no pointers; the fd it returns is owned by the
+        // NetlinkSocket, which closes it once
+        let fd = unsafe {
+            libc::socket(
+                libc::AF_NETLINK,
+                libc::SOCK_DGRAM | libc::SOCK_CLOEXEC,
+                libc::NETLINK_SOCK_DIAG,
+            )
+        };
+        if fd < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(NetlinkSocket { fd })
+    }
+
+    /// Joins the multicast groups in the `groups` bit mask.
+    fn bind(&self, groups: u32) -> io::Result<()> {
+        // SAFETY: sockaddr_nl is plain data, valid when all zero
+        let mut address: libc::sockaddr_nl = unsafe { mem::zeroed() };
+        address.nl_family = libc::AF_NETLINK as libc::sa_family_t;
+        address.nl_groups = groups;
+        // SAFETY: the address outlives the call and the length is its size
+        let bound = unsafe {
+            libc::bind(
+                self.fd,
+                &address as *const libc::sockaddr_nl as *const libc::sockaddr,
+                mem::size_of::<libc::sockaddr_nl>() as libc::socklen_t,
+            )
+        };
+        if bound < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(())
+    }
+
+    /// Sends a dump request and hands the payload of every reply message to
+    /// `on_message`.
+    fn dump<F: FnMut(&[u8])>(
+        &self,
+        request: &[u8],
+        mut on_message: F,
+    ) -> Result<(), ConnectionToolsError> {
+        self.send(request).map_err(sock_diag_error)?;
+        let mut buffer = vec![0u8; RECV_BUFFER_LEN];
+        loop {
+            let len = self.recv(&mut buffer).map_err(sock_diag_error)?;
+            if parse_messages(&buffer[..len], &mut on_message)? {
+                return Ok(());
+            }
+        }
+    }
+
+    fn send(&self, message: &[u8]) -> io::Result<()> {
+        // SAFETY: sockaddr_nl is plain data; all zero addresses the kernel
+        let mut kernel: libc::sockaddr_nl = unsafe { mem::zeroed() };
+        kernel.nl_family = libc::AF_NETLINK as libc::sa_family_t;
+        // SAFETY: the message and the address outlive the call, and the
+        // lengths are theirs
+        let sent = unsafe {
+            libc::sendto(
+                self.fd,
+                message.as_ptr() as *const libc::c_void,
+                message.len(),
+                0,
+                &kernel as *const libc::sockaddr_nl as *const libc::sockaddr,
+                mem::size_of::<libc::sockaddr_nl>() as libc::socklen_t,
+            )
+        };
+        if sent < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(())
+    }
+
+    fn recv(&self, buffer: &mut [u8]) -> io::Result<usize> {
+        loop {
+            // SAFETY: the kernel writes at most buffer.len() bytes into the
+            // buffer, which is borrowed mutably for the call
+            let len = unsafe {
+                libc::recv(
+                    self.fd,
+                    buffer.as_mut_ptr() as *mut libc::c_void,
+                    buffer.len(),
+                    0,
+                )
+            };
+            if len >= 0 {
+                return Ok(len as usize);
+            }
+            let err = io::Error::last_os_error();
+            if err.kind() != io::ErrorKind::Interrupted {
+                return Err(err);
+            }
+        }
+    }
+}
+
+impl Drop for NetlinkSocket {
+    fn drop(&mut self) {
+        // SAFETY: the fd is owned by this socket and closed nowhere else
+        unsafe {
+            libc::close(self.fd);
+        }
+    }
+}
+
+/// `nlmsghdr` of a dump request with a payload of `payload_len` bytes.
+fn request_header(payload_len: usize, seq: u32) -> Vec<u8> {
+    let len = NLMSG_HEADER_LEN + payload_len;
+    let mut message = Vec::with_capacity(len);
+    message.extend_from_slice(&(len as u32).to_ne_bytes());
+    message.extend_from_slice(&SOCK_DIAG_BY_FAMILY.to_ne_bytes());
+    message.extend_from_slice(&((libc::NLM_F_REQUEST | libc::NLM_F_DUMP) as u16).to_ne_bytes());
+    message.extend_from_slice(&seq.to_ne_bytes());
+    message.extend_from_slice(&0u32.to_ne_bytes());
+    message
+}
+
+/// A `nlmsghdr` followed by an `inet_diag_req_v2` asking for every socket of
+/// the given family and protocol.
+fn inet_request(family: u8, protocol: Protocol, seq: u32) -> Vec<u8> {
+    let mut message = request_header(REQUEST_LEN, seq);
+    let ip_protocol = match protocol {
+        Protocol::Tcp => libc::IPPROTO_TCP,
+        Protocol::Udp => libc::IPPROTO_UDP,
+    };
+    message.push(family);
+    message.push(ip_protocol as u8);
+    // idiag_ext asks for attribute `n` with bit `n - 1`
+    message.push((1 << (INET_DIAG_INFO - 1) | 1 << (INET_DIAG_SKMEMINFO - 1)) as u8);
+    message.push(0);
+    message.extend_from_slice(&ALL_STATES.to_ne_bytes());
+    // an empty inet_diag_sockid matches any socket
+    message.resize(NLMSG_HEADER_LEN + REQUEST_LEN, 0);
+    message
+}
+
+/// A `nlmsghdr` followed by a `unix_diag_req` asking for every Unix socket
+/// together with its name and peer.
+fn unix_request(seq: u32) -> Vec<u8> {
+    let mut message = request_header(UNIX_REQUEST_LEN, seq);
+    message.push(libc::AF_UNIX as u8);
+    message.extend_from_slice(&[0, 0, 0]);
+    message.extend_from_slice(&ALL_STATES.to_ne_bytes());
+    // udiag_ino
+    message.extend_from_slice(&0u32.to_ne_bytes());
+    message.extend_from_slice(&UNIX_SHOW.to_ne_bytes());
+    message.resize(NLMSG_HEADER_LEN + UNIX_REQUEST_LEN, 0);
+    message
+}
+
+/// Walks the messages of one datagram of a dump reply; returns true once the
+/// dump is complete.
+fn parse_messages<F: FnMut(&[u8])>(
+    mut buffer: &[u8],
+    on_message: &mut F,
+) -> Result<bool, ConnectionToolsError> {
+    while buffer.len() >= NLMSG_HEADER_LEN {
+        let len = read_u32(buffer, 0) as usize;
+        let kind = u16::from_ne_bytes([buffer[4], buffer[5]]);
+        if len < NLMSG_HEADER_LEN || len > buffer.len() {
+            return Err(ConnectionToolsError::FailToGetSocketsInfo {
+                message: "sock_diag: truncated netlink message".to_owned(),
+            });
+        }
+        let payload = &buffer[NLMSG_HEADER_LEN..len];
+
+        match i32::from(kind) {
+            libc::NLMSG_DONE => return Ok(true),
+            libc::NLMSG_ERROR => {
+                let errno = if payload.len() >= 4 {
+                    -(read_u32(payload, 0) as i32)
+                } else {
+                    libc::EIO
+                };
+                return Err(sock_diag_error(io::Error::from_raw_os_error(errno)));
+            }
+            _ if kind == SOCK_DIAG_BY_FAMILY => on_message(payload),
+            _ => {}
+        }
+
+        let aligned = (len + 3) & !3;
+        buffer = &buffer[aligned.min(buffer.len())..];
+    }
+    Ok(false)
+}
+
+/// `struct inet_diag_msg`: ports are in network byte order, addresses are raw
+/// bytes and everything else is in host byte order.
+fn parse_diag_msg(payload: &[u8], protocol: Protocol) -> Option<KernelSocket> {
+    if payload.len() < DIAG_MSG_LEN {
+        return None;
+    }
+    let family = i32::from(payload[0]);
+    let state = payload[1];
+    let sport = u16::from_be_bytes([payload[4], payload[5]]);
+    let dport = u16::from_be_bytes([payload[6], payload[7]]);
+    let src = &payload[8..24];
+    let dst = &payload[24..40];
+    let recv_queue = read_u32(payload, 56);
+    let send_queue = read_u32(payload, 60);
+    let uid = read_u32(payload, 64);
+    let inode = read_u32(payload, 68);
+
+    let address = |bytes: &[u8]| -> Option<IpAddr> {
+        match family {
+            libc::AF_INET => {
+                let octets: [u8; 4] = bytes[..4].try_into().ok()?;
+                Some(IpAddr::V4(Ipv4Addr::from(octets)))
+            }
+            libc::AF_INET6 => {
+                let octets: [u8; 16] = bytes.try_into().ok()?;
+                Some(IpAddr::V6(Ipv6Addr::from(octets)))
+            }
+            _ => None,
+        }
+    };
+
+    Some(KernelSocket {
+        protocol,
+        local: SocketAddr::new(address(src)?, sport),
+        remote: SocketAddr::new(address(dst)?, dport),
+        state,
+        uid,
+        inode,
+        queues: Some(Queues {
+            recv: recv_queue,
+            send: send_queue,
+        }),
+        tcp_info: None,
+        memory: None,
+    })
+    .map(|mut socket| {
+        for (kind, value) in attributes(&payload[DIAG_MSG_LEN..]) {
+            match kind {
+                INET_DIAG_INFO if protocol == Protocol::Tcp => {
+                    socket.tcp_info = parse_tcp_info(value)
+                }
+                INET_DIAG_SKMEMINFO => socket.memory = parse_memory(value),
+                _ => {}
+            }
+        }
+        socket
+    })
+}
+
+/// A destroy notification is an `inet_diag_msg` that names its protocol in an
+/// `INET_DIAG_PROTOCOL` attribute.
+fn parse_destroyed(payload: &[u8]) -> Option<KernelSocket> {
+    let protocol = attributes(payload.get(DIAG_MSG_LEN..)?)
+        .into_iter()
+        .find(|(kind, value)| *kind == INET_DIAG_PROTOCOL && !value.is_empty())
+        .and_then(|(_, value)| match i32::from(value[0]) {
+            libc::IPPROTO_TCP => Some(Protocol::Tcp),
+            libc::IPPROTO_UDP => Some(Protocol::Udp),
+            _ => None,
+        })?;
+    parse_diag_msg(payload, protocol)
+}
+
+/// `struct unix_diag_msg` followed by the `UNIX_DIAG_*` attributes.
+fn parse_unix_diag_msg(payload: &[u8], now: SystemTime) -> Option<UnixSocketRow> {
+    if payload.len() < UNIX_DIAG_MSG_LEN {
+        return None;
+    }
+    let socket_type = unix_type(u32::from(payload[1]))?;
+    // Unix sockets reuse the TCP state numbers
+    let state = match payload[2] {
+        10 => UnixState::Listen,
+        1 => UnixState::Connected,
+        2 => UnixState::Connecting,
+        _ => UnixState::Unconnected,
+    };
+    let inode = read_u32(payload, 4);
+
+    let mut path = None;
+    let mut peer_inode = None;
+    for (kind, value) in attributes(&payload[UNIX_DIAG_MSG_LEN..]) {
+        match kind {
+            UNIX_DIAG_NAME if !value.is_empty() => path = Some(unix_name(value)),
+            UNIX_DIAG_PEER if value.len() >= 4 => peer_inode = Some(read_u32(value, 0)),
+            _ => {}
+        }
+    }
+    Some(unix_row(socket_type, path, state, inode, peer_inode, now))
+}
+
+/// Abstract names start with a NUL byte instead of being a path.
+fn unix_name(value: &[u8]) -> String {
+    match value.split_first() {
+        Some((0, name)) => format!("@{}", String::from_utf8_lossy(name)),
+        _ => String::from_utf8_lossy(value)
+            .trim_end_matches('\0')
+            .to_owned(),
+    }
+}
+
+/// The `rtattr` list following a message, as (type, value) pairs.
+fn attributes(mut bytes: &[u8]) -> Vec<(u16, &[u8])> {
+    let mut attributes = Vec::new();
+    while bytes.len() >= RTATTR_HEADER_LEN {
+        let len = u16::from_ne_bytes([bytes[0], bytes[1]]) as usize;
+        let kind = u16::from_ne_bytes([bytes[2], bytes[3]]);
+        if len < RTATTR_HEADER_LEN || len > bytes.len() {
+            break;
+        }
+        attributes.push((kind, &bytes[RTATTR_HEADER_LEN..len]));
+        let aligned = (len + 3) & !3;
+        bytes = &bytes[aligned.min(bytes.len())..];
+    }
+    attributes
+}
+
+/// Offsets into `struct tcp_info` from `linux/tcp.h`. The struct only ever
+/// grows, so fields past the end of what an older kernel sends are `None`.
+fn parse_tcp_info(value: &[u8]) -> Option<TcpInfo> {
+    if value.len() < 104 {
+        return None;
+    }
+    let read_u64 = |offset: usize| -> Option<u64> {
+        value
+            .get(offset..offset + 8)
+            .and_then(|bytes| bytes.try_into().ok())
+            .map(u64::from_ne_bytes)
+    };
+    Some(TcpInfo {
+        rtt_us: read_u32(value, 68),
+        rttvar_us: read_u32(value, 72),
+        snd_cwnd: read_u32(value, 80),
+        retransmits: read_u32(value, 100),
+        lost: read_u32(value, 32),
+        bytes_acked: read_u64(120),
+        bytes_received: read_u64(128),
+        delivery_rate: read_u64(160),
+    })
+}
+
+fn parse_memory(value: &[u8]) -> Option<SocketMemory> {
+    if value.len() < 9 * 4 {
+        return None;
+    }
+    let field = |index: usize| read_u32(value, index * 4);
+    Some(SocketMemory {
+        rmem_alloc: field(0),
+        rcvbuf: field(1),
+        wmem_alloc: field(2),
+        sndbuf: field(3),
+        fwd_alloc: field(4),
+        wmem_queued: field(5),
+        optmem: field(6),
+        backlog: field(7),
+        drops: field(8),
+    })
+}
+
+fn read_u32(bytes: &[u8], offset: usize) -> u32 {
+    u32::from_ne_bytes([
+        bytes[offset],
+        bytes[offset + 1],
+        bytes[offset + 2],
+        bytes[offset + 3],
+    ])
+}
+
+#[cfg(test)]
+mod test {
+    use super::{
+        inet_request, parse_destroyed, parse_diag_msg, parse_messages, parse_unix_diag_msg,
+        KernelSocket, DIAG_MSG_LEN, INET_DIAG_INFO, INET_DIAG_PROTOCOL, INET_DIAG_SKMEMINFO,
+        NLMSG_HEADER_LEN, REQUEST_LEN, SOCK_DIAG_BY_FAMILY, UNIX_DIAG_MSG_LEN, UNIX_DIAG_NAME,
+        UNIX_DIAG_PEER,
+    };
+    use crate::errors::ConnectionToolsError;
+    use crate::sockets::{Protocol, Queues, UnixState, UnixType};
+    use std::net::SocketAddr;
+    use std::time::SystemTime;
+
+    fn parse_tcp(
+        datagram: &[u8],
+        sockets: &mut Vec<KernelSocket>,
+    ) -> Result<bool, ConnectionToolsError> {
+        parse_messages(datagram, &mut |payload: &[u8]| {
+            sockets.extend(parse_diag_msg(payload, Protocol::Tcp))
+        })
+    }
+
+    fn netlink_message(kind: u16, payload: &[u8]) -> Vec<u8> {
+        let mut message = Vec::new();
+        message.extend_from_slice(&((NLMSG_HEADER_LEN + payload.len()) as u32).to_ne_bytes());
+        message.extend_from_slice(&kind.to_ne_bytes());
+        message.extend_from_slice(&[0; 10]);
+        message.extend_from_slice(payload);
+        message
+    }
+
+    fn diag_msg(state: u8, local: [u8; 4], sport: u16, inode: u32) -> Vec<u8> {
+        let mut payload = vec![0u8; DIAG_MSG_LEN];
+        payload[0] = libc::AF_INET as u8;
+        payload[1] = state;
+        payload[4..6].copy_from_slice(&sport.to_be_bytes());
+        payload[8..12].copy_from_slice(&local);
+        payload[64..68].copy_from_slice(&1000u32.to_ne_bytes());
+        payload[68..72].copy_from_slice(&inode.to_ne_bytes());
+        payload
+    }
+
+    fn attribute(kind: u16, value: &[u8]) -> Vec<u8> {
+        let mut attribute = Vec::new();
+        attribute.extend_from_slice(&(4 + value.len() as u16).to_ne_bytes());
+        attribute.extend_from_slice(&kind.to_ne_bytes());
+        attribute.extend_from_slice(value);
+        attribute.resize((attribute.len() + 3) & !3, 0);
+        attribute
+    }
+
+    #[test]
+    fn request_layout() {
+        let request = inet_request(libc::AF_INET6 as u8, Protocol::Udp, 7);
+
+        assert_eq!(request.len(), NLMSG_HEADER_LEN + REQUEST_LEN);
+        assert_eq!(&request[4..6], &SOCK_DIAG_BY_FAMILY.to_ne_bytes());
+        assert_eq!(&request[8..12], &7u32.to_ne_bytes());
+        assert_eq!(request[16], libc::AF_INET6 as u8);
+        assert_eq!(request[17], libc::IPPROTO_UDP as u8);
+        assert_eq!(request[18], 0b0100_0010);
+        assert_eq!(&request[20..24], &[0xff; 4]);
+    }
+
+    #[test]
+    fn parse_dump() {
+        let mut datagram = netlink_message(
+            SOCK_DIAG_BY_FAMILY,
+            &diag_msg(10, [127, 0, 0, 1], 8080, 4242),
+        );
+        datagram.extend(netlink_message(
+            SOCK_DIAG_BY_FAMILY,
+            &diag_msg(1, [10, 0, 0, 2], 50000, 4343),
+        ));
+        let mut sockets = Vec::new();
+
+        assert!(!parse_tcp(&datagram, &mut sockets).unwrap());
+        assert_eq!(sockets.len(), 2);
+        assert_eq!(
+            sockets[0].local,
+            "127.0.0.1:8080".parse::<SocketAddr>().unwrap()
+        );
+        assert_eq!(sockets[0].state, 10);
+        assert_eq!(sockets[0].uid, 1000);
+        assert_eq!(sockets[1].inode, 4343);
+
+        let done = netlink_message(libc::NLMSG_DONE as u16, &[0; 4]);
+        assert!(parse_tcp(&done, &mut sockets).unwrap());
+    }
+
+    #[test]
+    fn parse_internals() {
+        let mut payload = diag_msg(1, [10, 0, 0, 2], 50000, 4343);
+        payload[56..60].copy_from_slice(&12u32.to_ne_bytes());
+        payload[60..64].copy_from_slice(&345u32.to_ne_bytes());
+        // tcp_info of an old kernel that stops before the byte counters
+        let mut tcp_info = vec![0u8; 104];
+        tcp_info[68..72].copy_from_slice(&1500u32.to_ne_bytes());
+        tcp_info[80..84].copy_from_slice(&10u32.to_ne_bytes());
+        tcp_info[100..104].copy_from_slice(&3u32.to_ne_bytes());
+        payload.extend(attribute(INET_DIAG_INFO, &tcp_info));
+        let memory: Vec<u8> = (0..9u32)
+            .flat_map(|field| field.to_ne_bytes().to_vec())
+            .collect();
+        payload.extend(attribute(INET_DIAG_SKMEMINFO, &memory));
+        let mut sockets = Vec::new();
+
+        parse_tcp(
+            &netlink_message(SOCK_DIAG_BY_FAMILY, &payload),
+            &mut sockets,
+        )
+        .unwrap();
+
+        let socket = &sockets[0];
+        assert_eq!(
+            socket.queues,
+            Some(Queues {
+                recv: 12,
+                send: 345
+            })
+        );
+        let tcp_info = socket.tcp_info.unwrap();
+        assert_eq!(tcp_info.rtt_us, 1500);
+        assert_eq!(tcp_info.snd_cwnd, 10);
+        assert_eq!(tcp_info.retransmits, 3);
+        assert_eq!(tcp_info.bytes_acked, None);
+        let memory = socket.memory.unwrap();
+        assert_eq!(memory.sndbuf, 3);
+        assert_eq!(memory.drops, 8);
+    }
+
+    #[test]
+    fn parse_destroy_notification() {
+        let mut tcp_info = vec![0u8; 136];
+        tcp_info[120..128].copy_from_slice(&2048u64.to_ne_bytes());
+        tcp_info[128..136].copy_from_slice(&512u64.to_ne_bytes());
+        let mut payload = diag_msg(7, [10, 0, 0, 2], 50000, 0);
+        payload.extend(attribute(INET_DIAG_PROTOCOL, &[libc::IPPROTO_TCP as u8]));
+        payload.extend(attribute(INET_DIAG_INFO, &tcp_info));
+
+        let socket = parse_destroyed(&payload).unwrap();
+
+        assert_eq!(socket.protocol, Protocol::Tcp);
+        assert_eq!(socket.state, 7);
+        let tcp_info = socket.tcp_info.unwrap();
+        assert_eq!(tcp_info.bytes_acked, Some(2048));
+        assert_eq!(tcp_info.bytes_received, Some(512));
+
+        let without_protocol = diag_msg(7, [10, 0, 0, 2], 50000, 0);
+        assert!(parse_destroyed(&without_protocol).is_none());
+    }
+
+    #[test]
+    fn netlink_error() {
+        let error = netlink_message(libc::NLMSG_ERROR as u16, &(-libc::EPERM).to_ne_bytes());
+
+        assert!(parse_tcp(&error, &mut Vec::new()).is_err());
+    }
+
+    #[test]
+    fn parse_unix_socket() {
+        let mut payload = vec![0u8; UNIX_DIAG_MSG_LEN];
+        payload[0] = libc::AF_UNIX as u8;
+        payload[1] = libc::SOCK_STREAM as u8;
+        payload[2] = 1;
+        payload[4..8].copy_from_slice(&777u32.to_ne_bytes());
+        payload.extend(attribute(UNIX_DIAG_NAME, b"\0dbus-abc"));
+        payload.extend(attribute(UNIX_DIAG_PEER, &778u32.to_ne_bytes()));
+
+        let row = parse_unix_diag_msg(&payload, SystemTime::now()).unwrap();
+
+        assert_eq!(row.socket_type, UnixType::Stream);
+        assert_eq!(row.state, UnixState::Connected);
+        assert_eq!(row.inode, 777);
+        assert_eq!(row.path.as_deref(), Some("@dbus-abc"));
+        assert_eq!(row.peer_inode, Some(778));
+    }
+}
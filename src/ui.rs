@@ -1,87 +1,212 @@
+use std::time::{Duration, SystemTime};
+
 use tui::backend::Backend;
-use tui::layout::Corner;
-use tui::layout::{Alignment, Constraint, Direction, Layout};
+use tui::buffer::Buffer;
+use tui::layout::{Alignment, Constraint, Direction, Layout, Rect};
 use tui::style::{Color, Modifier, Style};
-use tui::widgets::{Block, Borders, List, Paragraph, SelectableList, Table, Text, Widget};
+use tui::widgets::{
+    Axis, Block, Borders, Chart, Dataset, Marker, Paragraph, Row, Sparkline, Table, Tabs, Text,
+    Widget,
+};
 use tui::Frame;
 
-use crate::app::App;
+use crate::app::{App, Highlight, KillDialog, Tab};
+use crate::format::{
+    display_or_star, format_bytes, format_clock, format_duration, format_endpoint,
+    format_throughput, join_pids,
+};
+use crate::lifecycle::{Change, LifecycleEvent};
+use crate::metrics::{Metrics, Sample, CHARTED_STATES};
+use crate::signal::Signal;
+use crate::sockets::{Family, Protocol, RawSocketRow, SocketRow, TcpInfo, UnixSocketRow};
 
 pub fn draw<B: Backend>(f: &mut Frame<B>, app: &mut App) {
     let main_chunks = Layout::default()
         .direction(Direction::Vertical)
-        .constraints([Constraint::Percentage(50), Constraint::Percentage(50)].as_ref())
+        .constraints(
+            [
+                Constraint::Length(3),
+                Constraint::Min(0),
+                Constraint::Length(1),
+            ]
+            .as_ref(),
+        )
         .split(f.size());
 
+    let mut title = format!("Poke ({})", app.source);
+    if let Some(shown_at) = app.last_refresh_at {
+        title.push_str(&format!(" {}", format_clock(shown_at)));
+    }
+    if let Some(behind) = app.behind_live() {
+        title.push_str(&format!(", paused {} behind live", format_duration(behind)));
+    }
+    Tabs::default()
+        .block(Block::default().borders(Borders::ALL).title(&title))
+        .titles(&app.tabs.titles)
+        .select(app.tabs.index)
+        .style(Style::default().fg(Color::Cyan))
+        .highlight_style(Style::default().fg(Color::Yellow).modifier(Modifier::BOLD))
+        .render(f, main_chunks[0]);
+
+    match app.tab() {
+        Tab::Sockets => draw_sockets_tab(f, main_chunks[1], app),
+        Tab::Listening => draw_listening_tab(f, main_chunks[1], app),
+        Tab::Processes => draw_processes_tab(f, main_chunks[1], app),
+        Tab::Stats => draw_stats_tab(f, main_chunks[1], app),
+        Tab::Events => draw_events_tab(f, main_chunks[1], app),
+    }
+
+    draw_status_line(f, main_chunks[2], app);
+    if let Some(dialog) = &app.kill_dialog {
+        draw_kill_dialog(f, main_chunks[1], dialog);
+    }
+}
+
+/// Blanks the cells under a dialog.
+struct Clear;
+
+impl Widget for Clear {
+    fn draw(&mut self, area: Rect, buf: &mut Buffer) {
+        for y in area.top()..area.bottom() {
+            for x in area.left()..area.right() {
+                buf.get_mut(x, y).reset();
+            }
+        }
+    }
+}
+
+/// Centered over `area`, with the signals side by side and the chosen one
+/// highlighted.
+fn draw_kill_dialog<B: Backend>(f: &mut Frame<B>, area: Rect, dialog: &KillDialog) {
+    let owners: Vec<String> = dialog
+        .owners
+        .iter()
+        .map(|(key, name)| match name {
+            Some(name) => format!("{} ({})", key.pid, name),
+            None => key.pid.to_string(),
+        })
+        .collect();
+    let mut text = vec![Text::raw(format!(
+        "Send a signal to {}, owning {}?\n\n",
+        owners.join(", "),
+        dialog.socket
+    ))];
+    for signal in Signal::ALL.iter() {
+        let style = if *signal == dialog.signal {
+            Style::default().fg(Color::Black).bg(Color::Yellow)
+        } else {
+            Style::default()
+        };
+        text.push(Text::styled(format!(" {} ", signal), style));
+        text.push(Text::raw(" "));
+    }
+    text.push(Text::raw(
+        "\n\n←/→ choose the signal, Enter sends it, Esc cancels",
+    ));
+
+    let width = area.width.min(64);
+    let height = area.height.min(9);
+    let dialog_area = Rect::new(
+        area.x + (area.width - width) / 2,
+        area.y + (area.height - height) / 2,
+        width,
+        height,
+    );
+    Clear.render(f, dialog_area);
+    Paragraph::new(text.iter())
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title("Kill")
+                .border_style(Style::default().fg(Color::Red)),
+        )
+        .wrap(true)
+        .render(f, dialog_area);
+}
+
+fn draw_sockets_tab<B: Backend>(f: &mut Frame<B>, area: Rect, app: &App) {
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Percentage(60), Constraint::Percentage(40)].as_ref())
+        .split(area);
+
     {
-        let sockets_chunk = main_chunks[0];
+        let sockets_chunk = chunks[0];
 
+        let mut title = "Open sockets".to_owned();
+        if let Some(throughput) = app.total_throughput {
+            title.push_str(&format!(" {}", format_throughput(throughput)));
+        }
         Block::default()
             .borders(Borders::ALL)
-            .title("Open sockets")
+            .title(&title)
             .render(f, sockets_chunk);
 
-        let sockets_info_layout = Layout::default()
+        let socket_tables_layout = Layout::default()
             .direction(Direction::Vertical)
             .margin(1)
-            .constraints([Constraint::Percentage(90), Constraint::Min(2)].as_ref())
+            .constraints([Constraint::Percentage(60), Constraint::Percentage(40)].as_ref())
             .split(sockets_chunk);
 
         let socket_connections_layout = Layout::default()
             .direction(Direction::Horizontal)
             .constraints([Constraint::Percentage(50), Constraint::Percentage(50)].as_ref())
-            .split(sockets_info_layout[0]);
+            .split(socket_tables_layout[0]);
+
+        let local_sockets_layout = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Percentage(55), Constraint::Percentage(45)].as_ref())
+            .split(socket_tables_layout[1]);
 
         let tcp_sockets_layout = socket_connections_layout[0];
         let udp_sockets_layout = socket_connections_layout[1];
-        let text_socket_info_layout = sockets_info_layout[1];
+        let unix_sockets_layout = local_sockets_layout[0];
+        let raw_sockets_layout = local_sockets_layout[1];
 
-        SelectableList::default()
-            .block(
-                Block::default()
-                    .title("TCP")
-                    .title_style(Style::default().fg(Color::Magenta).modifier(Modifier::BOLD))
-                    .borders(Borders::ALL),
-            )
-            .items(&app.tcp_sockets)
-            .select(app.selected_tcp())
-            .highlight_style(
-                Style::default()
-                    .fg(Color::LightGreen)
-                    .modifier(Modifier::BOLD),
-            )
-            .highlight_symbol(">")
-            .render(f, tcp_sockets_layout);
+        draw_sockets_table(
+            f,
+            tcp_sockets_layout,
+            app,
+            Protocol::Tcp,
+            Style::default()
+                .fg(Color::LightGreen)
+                .modifier(Modifier::BOLD),
+        );
 
-        SelectableList::default()
-            .block(
-                Block::default()
-                    .title("UDP")
-                    .title_style(Style::default().fg(Color::Magenta).modifier(Modifier::BOLD))
-                    .borders(Borders::ALL),
-            )
-            .items(&app.udp_sockets)
-            .select(app.selected_udp())
-            .highlight_style(
-                Style::default()
-                    .fg(Color::LightYellow)
-                    .modifier(Modifier::BOLD),
-            )
-            .highlight_symbol(">")
-            .render(f, udp_sockets_layout);
+        draw_sockets_table(
+            f,
+            udp_sockets_layout,
+            app,
+            Protocol::Udp,
+            Style::default()
+                .fg(Color::LightYellow)
+                .modifier(Modifier::BOLD),
+        );
 
-        let text = [Text::raw(format!(
-            "TCP count: {}; UDP count: {}; <debug> current selection: {:?}",
-            app.tcp_sockets_count, app.udp_sockets_count, app.selected_type
-        ))];
-        Paragraph::new(text.iter()).render(f, text_socket_info_layout);
+        draw_unix_sockets_table(
+            f,
+            unix_sockets_layout,
+            app,
+            Style::default()
+                .fg(Color::LightCyan)
+                .modifier(Modifier::BOLD),
+        );
+
+        draw_raw_sockets_table(
+            f,
+            raw_sockets_layout,
+            app,
+            Style::default()
+                .fg(Color::LightRed)
+                .modifier(Modifier::BOLD),
+        );
 
         //todo: dead code, but I want to save it for later
         let is_error = false;
         if is_error {
             let error_message = "wow, error happens!";
             let text = [Text::styled(
-                format!("{}", error_message),
+                error_message.to_string(),
                 Style::default().fg(Color::Red),
             )];
             Paragraph::new(text.iter())
@@ -98,10 +223,7 @@ pub fn draw<B: Backend>(f: &mut Frame<B>, app: &mut App) {
     }
 
     {
-        let text = [Text::styled(
-            app.selected_socket_info(),
-            Style::default().fg(Color::White),
-        )];
+        let text = [Text::styled(app.selected_socket_info(), app.info_style)];
 
         Paragraph::new(text.iter())
             .block(
@@ -111,6 +233,825 @@ pub fn draw<B: Backend>(f: &mut Frame<B>, app: &mut App) {
                     .title_style(Style::default().fg(Color::Magenta).modifier(Modifier::BOLD)),
             )
             .wrap(true)
-            .render(f, main_chunks[1]);
+            .render(f, chunks[1]);
+    }
+}
+
+/// Socket counts, refresh time and filter, shown under every tab.
+fn draw_status_line<B: Backend>(f: &mut Frame<B>, area: Rect, app: &App) {
+    let counts = if app.is_filtered() {
+        format!(
+            "TCP: {} of {}; UDP: {} of {}; Unix: {} of {}; Raw: {} of {}",
+            app.tcp_visible_count(),
+            app.tcp_sockets_count,
+            app.udp_visible_count(),
+            app.udp_sockets_count,
+            app.unix_visible_count(),
+            app.unix_sockets_count,
+            app.raw_visible_count(),
+            app.raw_sockets_count
+        )
+    } else {
+        format!(
+            "TCP count: {}; UDP count: {}; Unix count: {}; Raw count: {}",
+            app.tcp_sockets_count,
+            app.udp_sockets_count,
+            app.unix_sockets_count,
+            app.raw_sockets_count
+        )
+    };
+    let mut refresh = if app.refreshing {
+        "; refreshing…".to_owned()
+    } else {
+        app.last_refresh_duration
+            .map(|duration| format!("; refreshed in {} ms", duration.as_millis()))
+            .unwrap_or_default()
+    };
+    match &app.replay {
+        Some(player) => {
+            refresh.push_str(&format!(
+                "; replaying snapshot {} of {}",
+                player.position() + 1,
+                player.len()
+            ));
+            if player.paused {
+                refresh.push_str(", paused");
+            }
+        }
+        None if app.behind_live().is_some() => {}
+        None => {
+            let refresh_age = app
+                .last_refresh_at
+                .and_then(|last_refresh_at| last_refresh_at.elapsed().ok())
+                .unwrap_or_default();
+            if refresh_age.as_secs() > 1 {
+                refresh.push_str(&format!(", {} s ago", refresh_age.as_secs()));
+            }
+        }
+    }
+    if app.replay.is_none() {
+        refresh.push_str(&format!(
+            "; every {}",
+            format_interval(app.refresh_interval)
+        ));
+    }
+    if let Some(Ok(())) = &app.watching_destroyed {
+        refresh.push_str("; watching destroyed sockets");
+    }
+    if let Some(Ok(path)) = &app.recording {
+        refresh.push_str(&format!("; recording to {}", path));
+    }
+    let mut text = vec![Text::raw(counts), Text::raw(refresh)];
+    if app.filter_editing || !app.filter_query.is_empty() {
+        let cursor = if app.filter_editing { "_" } else { "" };
+        text.push(Text::raw(format!("; /{}{}", app.filter_query, cursor)));
+    }
+    match &app.last_export {
+        Some(Ok(path)) => text.push(Text::raw(format!("; exported to {}", path))),
+        Some(Err(err)) => text.push(Text::styled(format!("  {}", err), app.error_style)),
+        None => {}
+    }
+    match &app.last_signal {
+        Some(Ok(sent)) => text.push(Text::raw(format!("; sent {}", sent))),
+        Some(Err(err)) => text.push(Text::styled(format!("  {}", err), app.error_style)),
+        None => {}
+    }
+    if let Some(Err(err)) = &app.watching_destroyed {
+        text.push(Text::styled(format!("  {}", err), app.error_style));
+    }
+    if let Some(Err(err)) = &app.recording {
+        text.push(Text::styled(format!("  {}", err), app.error_style));
+    }
+    if let Some(filter_error) = &app.filter_error {
+        text.push(Text::styled(format!("  {}", filter_error), app.error_style));
+    }
+    Paragraph::new(text.iter()).render(f, area);
+}
+
+const LISTENING_COLUMNS: [(&str, Constraint); 6] = [
+    ("Proto", Constraint::Length(5)),
+    ("IP", Constraint::Length(4)),
+    ("Local address", Constraint::Percentage(30)),
+    ("Port", Constraint::Length(5)),
+    ("PIDs", Constraint::Length(12)),
+    ("Process", Constraint::Min(8)),
+];
+
+fn draw_listening_tab<B: Backend>(f: &mut Frame<B>, area: Rect, app: &App) {
+    let rows = app.listening_sockets();
+    let title = format!("Listening TCP and bound UDP sockets: {}", rows.len());
+    let view = TableView {
+        title: &title,
+        columns: &LISTENING_COLUMNS,
+        selected: None,
+        highlight_style: Style::default(),
+    };
+    let scroll = app.scroll.min(rows.len());
+    draw_table(f, area, view, &rows[scroll..], |row| {
+        vec![
+            row.protocol.to_string(),
+            row.family.to_string(),
+            row.local_addr.to_string(),
+            row.local_port.to_string(),
+            join_pids(&row.pids),
+            row.process_name.clone().unwrap_or_default(),
+        ]
+    });
+}
+
+const EVENT_COLUMNS: [(&str, Constraint); 10] = [
+    ("Time", Constraint::Length(8)),
+    ("Event", Constraint::Length(6)),
+    ("Proto", Constraint::Length(5)),
+    ("Local address", Constraint::Percentage(20)),
+    ("Remote address", Constraint::Percentage(20)),
+    ("State", Constraint::Length(25)),
+    ("Lived", Constraint::Length(7)),
+    ("Received / sent", Constraint::Length(21)),
+    ("PIDs", Constraint::Length(7)),
+    ("Process", Constraint::Min(8)),
+];
+
+/// Bytes received and sent over a TCP connection so far, or in total for a
+/// closed one.
+fn transferred(socket: &SocketRow) -> String {
+    match socket.tcp_info {
+        Some(TcpInfo {
+            bytes_received: Some(received),
+            bytes_acked: Some(sent),
+            ..
+        }) => format!("{} / {}", format_bytes(received), format_bytes(sent)),
+        _ => "*".to_owned(),
+    }
+}
+
+fn draw_events_tab<B: Backend>(f: &mut Frame<B>, area: Rect, app: &App) {
+    let events = app.events();
+    let title = format!("Events, newest first: {}", events.len());
+    let view = TableView {
+        title: &title,
+        columns: &EVENT_COLUMNS,
+        selected: None,
+        highlight_style: Style::default(),
+    };
+    let scroll = app.scroll.min(events.len());
+    let style = |event: &&LifecycleEvent| match event.change {
+        Change::Opened => Some(app.warning_style),
+        Change::Closed => Some(ghost_style(app)),
+        Change::StateChanged { .. } => None,
+    };
+    let cells = |event: &&LifecycleEvent| {
+        let socket = &event.socket;
+        let remote = match (socket.remote_addr, socket.remote_port) {
+            (Some(remote_addr), Some(remote_port)) => format_endpoint(remote_addr, remote_port),
+            _ => "*".to_owned(),
+        };
+        let state = match event.change {
+            Change::StateChanged { from, to } => format!("{} → {}", from, to),
+            _ => display_or_star(&socket.state),
+        };
+        vec![
+            format_clock(event.at),
+            event.change.to_string(),
+            socket.protocol.to_string(),
+            format_endpoint(socket.local_addr, socket.local_port),
+            remote,
+            state,
+            display_or_star(&event.lived().map(format_duration)),
+            transferred(socket),
+            join_pids(&socket.pids),
+            socket.process_name.clone().unwrap_or_default(),
+        ]
+    };
+    draw_styled_table(f, area, view, &events[scroll..], cells, style);
+}
+
+const PROCESS_SUMMARY_COLUMNS: [(&str, Constraint); 8] = [
+    ("PID", Constraint::Length(7)),
+    ("Process", Constraint::Percentage(20)),
+    ("TCP", Constraint::Length(5)),
+    ("UDP", Constraint::Length(5)),
+    ("Unix", Constraint::Length(5)),
+    ("Raw", Constraint::Length(5)),
+    ("Listening ports", Constraint::Percentage(30)),
+    ("Throughput", Constraint::Min(10)),
+];
+
+fn draw_processes_tab<B: Backend>(f: &mut Frame<B>, area: Rect, app: &App) {
+    let summaries = app.process_summaries();
+    let title = format!("Processes owning sockets: {}", summaries.len());
+    let view = TableView {
+        title: &title,
+        columns: &PROCESS_SUMMARY_COLUMNS,
+        selected: None,
+        highlight_style: Style::default(),
+    };
+    let scroll = app.scroll.min(summaries.len());
+    draw_table(f, area, view, &summaries[scroll..], |summary| {
+        let listening = summary
+            .listening
+            .iter()
+            .map(|port| port.to_string())
+            .collect::<Vec<String>>()
+            .join(",");
+        vec![
+            summary.pid.to_string(),
+            summary.name.clone().unwrap_or_default(),
+            summary.tcp.to_string(),
+            summary.udp.to_string(),
+            summary.unix.to_string(),
+            summary.raw.to_string(),
+            listening,
+            display_or_star(&summary.throughput.map(format_throughput)),
+        ]
+    });
+}
+
+fn draw_stats_tab<B: Backend>(f: &mut Frame<B>, area: Rect, app: &App) {
+    let rows = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints(
+            [
+                Constraint::Length(5),
+                Constraint::Percentage(50),
+                Constraint::Min(0),
+            ]
+            .as_ref(),
+        )
+        .split(area);
+
+    draw_sparklines(f, rows[0], &app.metrics);
+    draw_states_chart(f, rows[1], &app.metrics);
+
+    let chunks = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Percentage(40), Constraint::Percentage(60)].as_ref())
+        .split(rows[2]);
+
+    let states = app.tcp_state_counts();
+    let view = TableView {
+        title: "TCP states",
+        columns: &[
+            ("State", Constraint::Length(12)),
+            ("Count", Constraint::Min(6)),
+        ],
+        selected: None,
+        highlight_style: Style::default(),
+    };
+    draw_table(f, chunks[0], view, &states, |(state, count)| {
+        vec![state.to_string(), count.to_string()]
+    });
+
+    let inet = app.tcp_sockets().into_iter().chain(app.udp_sockets());
+    let (ipv4, ipv6) = inet.fold((0, 0), |(ipv4, ipv6), row| match row.family {
+        Family::Ipv4 => (ipv4 + 1, ipv6),
+        Family::Ipv6 => (ipv4, ipv6 + 1),
+    });
+    let mut totals = format!(
+        "TCP: {}\nUDP: {}\nUnix: {}\nRaw, packet and netlink: {}\n\nIPv4: {}\nIPv6: {}\n\nprocesses owning sockets: {}\n",
+        app.tcp_visible_count(),
+        app.udp_visible_count(),
+        app.unix_visible_count(),
+        app.raw_visible_count(),
+        ipv4,
+        ipv6,
+        app.process_summaries().len()
+    );
+    if let Some(throughput) = app.total_throughput {
+        totals.push_str(&format!("throughput: {}\n", format_throughput(throughput)));
+    }
+    let text = [Text::raw(totals)];
+    Paragraph::new(text.iter())
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title("Totals")
+                .title_style(Style::default().fg(Color::Magenta).modifier(Modifier::BOLD)),
+        )
+        .wrap(true)
+        .render(f, chunks[1]);
+}
+
+/// Colors of the `CHARTED_STATES` lines.
+const STATE_COLORS: [Color; CHARTED_STATES.len()] = [
+    Color::LightGreen,
+    Color::LightBlue,
+    Color::Gray,
+    Color::LightRed,
+];
+
+type SampleValue = fn(&Sample) -> u64;
+
+fn draw_sparklines<B: Backend>(f: &mut Frame<B>, area: Rect, metrics: &Metrics) {
+    let chunks = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Percentage(25); 4].as_ref())
+        .split(area);
+
+    let last = metrics.last();
+    let sparklines: [(String, SampleValue, Color); 4] = [
+        (
+            format!("TCP {}", last.map(|sample| sample.tcp).unwrap_or_default()),
+            |sample| sample.tcp as u64,
+            Color::LightGreen,
+        ),
+        (
+            format!("UDP {}", last.map(|sample| sample.udp).unwrap_or_default()),
+            |sample| sample.udp as u64,
+            Color::LightYellow,
+        ),
+        (
+            format!(
+                "Opened/s {:.1}",
+                last.map(|sample| sample.opened).unwrap_or_default()
+            ),
+            |sample| sample.opened.round() as u64,
+            Color::LightCyan,
+        ),
+        (
+            format!(
+                "Closed/s {:.1}",
+                last.map(|sample| sample.closed).unwrap_or_default()
+            ),
+            |sample| sample.closed.round() as u64,
+            Color::LightRed,
+        ),
+    ];
+
+    for ((title, value, color), chunk) in sparklines.iter().zip(chunks) {
+        // a sparkline draws its data from the left, so only the newest
+        // samples that fit are passed
+        let width = chunk.width.saturating_sub(2) as usize;
+        let skip = metrics.samples().len().saturating_sub(width);
+        let data: Vec<u64> = metrics.samples().skip(skip).map(value).collect();
+        Sparkline::default()
+            .block(Block::default().borders(Borders::ALL).title(title))
+            .data(&data)
+            .style(Style::default().fg(*color))
+            .render(f, chunk);
+    }
+}
+
+/// Charted TCP states over the kept history, in seconds before the last
+/// refresh.
+fn draw_states_chart<B: Backend>(f: &mut Frame<B>, area: Rect, metrics: &Metrics) {
+    let newest = match metrics.last() {
+        Some(last) => last.taken_at,
+        None => {
+            Block::default()
+                .borders(Borders::ALL)
+                .title("TCP states over time")
+                .render(f, area);
+            return;
+        }
+    };
+    let age = |sample: &Sample| {
+        newest
+            .duration_since(sample.taken_at)
+            .map(|age| -age.as_secs_f64())
+            .unwrap_or_default()
+    };
+    let oldest = metrics
+        .samples()
+        .next()
+        .map(age)
+        .unwrap_or_default()
+        .min(-1.0);
+    let lines: Vec<Vec<(f64, f64)>> = (0..CHARTED_STATES.len())
+        .map(|index| {
+            metrics
+                .samples()
+                .map(|sample| (age(sample), sample.states[index] as f64))
+                .collect()
+        })
+        .collect();
+    let max = metrics
+        .samples()
+        .flat_map(|sample| sample.states.iter().copied())
+        .max()
+        .unwrap_or_default()
+        .max(1) as f64;
+
+    let names: Vec<String> = CHARTED_STATES
+        .iter()
+        .map(|state| state.to_string())
+        .collect();
+    let datasets: Vec<Dataset> = lines
+        .iter()
+        .zip(&names)
+        .zip(STATE_COLORS.iter())
+        .map(|((line, name), color)| {
+            Dataset::default()
+                .name(name)
+                .marker(Marker::Braille)
+                .style(Style::default().fg(*color))
+                .data(line)
+        })
+        .collect();
+    let x_labels = [format!("{:.0}s", oldest), "now".to_owned()];
+    let y_labels = ["0".to_owned(), format!("{:.0}", max)];
+
+    Chart::default()
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title("TCP states over time")
+                .title_style(Style::default().fg(Color::Magenta).modifier(Modifier::BOLD)),
+        )
+        .x_axis(
+            Axis::default()
+                .bounds([oldest, 0.0])
+                .labels(&x_labels)
+                .style(Style::default().fg(Color::Gray)),
+        )
+        .y_axis(
+            Axis::default()
+                .bounds([0.0, max])
+                .labels(&y_labels)
+                .style(Style::default().fg(Color::Gray)),
+        )
+        .datasets(&datasets)
+        .render(f, area);
+}
+
+const UDP_COLUMNS: [(&str, Constraint); 3] = [
+    ("IP", Constraint::Length(4)),
+    ("Local address", Constraint::Percentage(40)),
+    ("Port", Constraint::Length(5)),
+];
+/// Shown with `i`, between the socket and the process columns.
+const QUEUE_COLUMNS: [(&str, Constraint); 2] = [
+    ("Recv-Q", Constraint::Length(6)),
+    ("Send-Q", Constraint::Length(6)),
+];
+const TCP_INFO_COLUMNS: [(&str, Constraint); 3] = [
+    ("RTT ms", Constraint::Length(7)),
+    ("Cwnd", Constraint::Length(5)),
+    ("Retr", Constraint::Length(5)),
+];
+const THROUGHPUT_COLUMNS: [(&str, Constraint); 2] = [
+    ("In/s", Constraint::Length(10)),
+    ("Out/s", Constraint::Length(10)),
+];
+const UNIX_COLUMNS: [(&str, Constraint); 8] = [
+    ("Type", Constraint::Length(9)),
+    ("State", Constraint::Length(13)),
+    ("Path", Constraint::Percentage(28)),
+    ("Inode", Constraint::Length(8)),
+    ("Peer", Constraint::Length(8)),
+    ("Peer process", Constraint::Percentage(12)),
+    ("PIDs", Constraint::Length(7)),
+    ("Process", Constraint::Min(8)),
+];
+const UNIX_WIDE_ONLY: [&str; 2] = ["Inode", "Peer process"];
+/// Narrower Unix panes squeeze the process column out of the wide layout.
+const UNIX_WIDE_WIDTH: u16 = 100;
+const RAW_COLUMNS: [(&str, Constraint); 7] = [
+    ("Kind", Constraint::Length(7)),
+    ("Proto", Constraint::Length(9)),
+    ("Bound to", Constraint::Percentage(25)),
+    ("UID", Constraint::Length(5)),
+    ("Inode", Constraint::Length(8)),
+    ("PIDs", Constraint::Length(7)),
+    ("Process", Constraint::Min(8)),
+];
+/// Time since the socket was first seen, before the process columns.
+const AGE_COLUMN: (&str, Constraint) = ("Age", Constraint::Length(6));
+const PROCESS_COLUMNS: [(&str, Constraint); 2] = [
+    ("PIDs", Constraint::Length(7)),
+    ("Process", Constraint::Min(8)),
+];
+
+/// Optional column groups of the socket tables.
+#[derive(Clone, Copy)]
+struct Extras {
+    internals: bool,
+    throughput: bool,
+    age: bool,
+}
+
+/// Share of the TCP pane each address takes, the narrow one making room
+/// for the optional columns.
+const ADDRESS_PERCENTAGES: [u16; 2] = [20, 12];
+
+fn columns(
+    protocol: Protocol,
+    extras: Extras,
+    address_percentage: u16,
+) -> Vec<(&'static str, Constraint)> {
+    let mut columns = match protocol {
+        Protocol::Tcp => {
+            let address = Constraint::Percentage(address_percentage);
+            vec![
+                ("IP", Constraint::Length(4)),
+                ("Local address", address),
+                ("Port", Constraint::Length(5)),
+                ("Remote address", address),
+                ("Port", Constraint::Length(5)),
+                ("State", Constraint::Length(11)),
+            ]
+        }
+        Protocol::Udp => UDP_COLUMNS.to_vec(),
+    };
+    if extras.internals {
+        columns.extend_from_slice(&QUEUE_COLUMNS);
+        if protocol == Protocol::Tcp {
+            columns.extend_from_slice(&TCP_INFO_COLUMNS);
+        }
+    }
+    if extras.throughput && protocol == Protocol::Tcp {
+        columns.extend_from_slice(&THROUGHPUT_COLUMNS);
+    }
+    if extras.age {
+        columns.push(AGE_COLUMN);
+    }
+    columns.extend_from_slice(&PROCESS_COLUMNS);
+    columns
+}
+
+/// Whether the columns and the spacing between them fit in `width`, with
+/// percentages taken of `width` as the table lays them out.
+fn fits(columns: &[(&str, Constraint)], width: u16) -> bool {
+    let cells: u16 = columns
+        .iter()
+        .map(|(_, constraint)| match constraint {
+            Constraint::Length(cells) | Constraint::Min(cells) => *cells,
+            Constraint::Percentage(percentage) => width * percentage / 100,
+            _ => 0,
+        })
+        .sum();
+    cells + columns.len() as u16 - 1 <= width
+}
+
+/// Height taken by the table borders, the header line and the gap below it.
+const TABLE_DECORATION_HEIGHT: u16 = 4;
+
+fn draw_sockets_table<B: Backend>(
+    f: &mut Frame<B>,
+    area: Rect,
+    app: &App,
+    protocol: Protocol,
+    highlight_style: Style,
+) {
+    let (rows, selected) = app.highlighted_sockets(protocol);
+    // optional columns are dropped rather than overflowing a narrow pane,
+    // the throughput first and the age last
+    let wanted = Extras {
+        internals: app.show_internals,
+        throughput: app.total_throughput.is_some(),
+        age: true,
+    };
+    let without_throughput = Extras {
+        throughput: false,
+        ..wanted
+    };
+    let narrowest = Extras {
+        internals: false,
+        throughput: false,
+        age: false,
+    };
+    let candidates = [
+        wanted,
+        without_throughput,
+        Extras {
+            internals: false,
+            ..without_throughput
+        },
+    ];
+    let inner_width = area.width.saturating_sub(2);
+    let (extras, address_percentage) = candidates
+        .iter()
+        .flat_map(|extras| {
+            ADDRESS_PERCENTAGES
+                .iter()
+                .map(move |percentage| (*extras, *percentage))
+        })
+        .find(|(extras, percentage)| fits(&columns(protocol, *extras, *percentage), inner_width))
+        .unwrap_or((narrowest, ADDRESS_PERCENTAGES[1]));
+    let title = format!("{} [{}]", protocol, app.sorting);
+    let view = TableView {
+        title: &title,
+        columns: &columns(protocol, extras, address_percentage),
+        selected,
+        highlight_style,
+    };
+    let now = app.last_refresh_at.unwrap_or_else(SystemTime::now);
+    draw_styled_table(
+        f,
+        area,
+        view,
+        &rows,
+        |(row, _)| socket_row_cells(row, extras, now),
+        |(_, highlight)| match highlight {
+            Highlight::None => None,
+            Highlight::New => Some(app.warning_style),
+            Highlight::Closed => Some(ghost_style(app)),
+        },
+    );
+}
+
+/// `250 ms` below a second, `2 s` from there.
+fn format_interval(interval: Duration) -> String {
+    if interval < Duration::from_secs(1) {
+        format!("{} ms", interval.as_millis())
+    } else {
+        format!("{} s", interval.as_secs_f64())
+    }
+}
+
+/// Closed sockets are dimmed on top of the critical color.
+fn ghost_style(app: &App) -> Style {
+    app.critical_style.modifier(Modifier::DIM)
+}
+
+/// Frame, header and selection of a socket pane.
+struct TableView<'a> {
+    title: &'a str,
+    columns: &'a [(&'a str, Constraint)],
+    selected: Option<usize>,
+    highlight_style: Style,
+}
+
+fn draw_table<B: Backend, R>(
+    f: &mut Frame<B>,
+    area: Rect,
+    view: TableView,
+    rows: &[R],
+    cells: impl Fn(&R) -> Vec<String>,
+) {
+    draw_styled_table(f, area, view, rows, cells, |_| None);
+}
+
+/// Like `draw_table`, with `style` picking the colors of the unselected rows.
+fn draw_styled_table<B: Backend, R>(
+    f: &mut Frame<B>,
+    area: Rect,
+    view: TableView,
+    rows: &[R],
+    cells: impl Fn(&R) -> Vec<String>,
+    style: impl Fn(&R) -> Option<Style>,
+) {
+    let mut block = Block::default()
+        .title(view.title)
+        .title_style(Style::default().fg(Color::Magenta).modifier(Modifier::BOLD))
+        .borders(Borders::ALL);
+
+    let (header, widths): (Vec<&str>, Vec<Constraint>) = view.columns.iter().copied().unzip();
+
+    // tui's Table can't lay out columns narrower than its spacing, so only
+    // draw the frame when the pane is squeezed.
+    if area.width < 2 + widths.len() as u16 || area.height <= TABLE_DECORATION_HEIGHT {
+        block.render(f, area);
+        return;
+    }
+
+    let visible_rows = (area.height - TABLE_DECORATION_HEIGHT) as usize;
+    let offset = match view.selected {
+        Some(selected) if selected >= visible_rows => selected - visible_rows + 1,
+        _ => 0,
+    };
+
+    let table_rows = rows
+        .iter()
+        .enumerate()
+        .skip(offset)
+        .take(visible_rows)
+        .map(|(index, row)| {
+            let cells = cells(row).into_iter();
+            if Some(index) == view.selected {
+                Row::StyledData(cells, view.highlight_style)
+            } else {
+                match style(row) {
+                    Some(style) => Row::StyledData(cells, style),
+                    None => Row::Data(cells),
+                }
+            }
+        });
+
+    Table::new(header.iter(), table_rows)
+        .block(block)
+        .header_style(Style::default().fg(Color::Yellow))
+        .widths(&widths)
+        .column_spacing(1)
+        .render(f, area);
+}
+
+fn draw_unix_sockets_table<B: Backend>(
+    f: &mut Frame<B>,
+    area: Rect,
+    app: &App,
+    highlight_style: Style,
+) {
+    let rows = app.unix_sockets();
+    // the inode and the peer process are left out when the pane is narrow
+    let wide = area.width >= UNIX_WIDE_WIDTH;
+    let columns: Vec<(&str, Constraint)> = UNIX_COLUMNS
+        .iter()
+        .copied()
+        .filter(|(name, _)| wide || !UNIX_WIDE_ONLY.contains(name))
+        .collect();
+    let title = format!("Unix [{}]", app.sorting);
+    let view = TableView {
+        title: &title,
+        columns: &columns,
+        selected: app.selected_unix(),
+        highlight_style,
+    };
+    draw_table(f, area, view, &rows, |row| {
+        unix_row_cells(row, app.unix_peer(row), wide)
+    });
+}
+
+fn draw_raw_sockets_table<B: Backend>(
+    f: &mut Frame<B>,
+    area: Rect,
+    app: &App,
+    highlight_style: Style,
+) {
+    let rows = app.raw_sockets();
+    let title = format!("Raw, packet and netlink [{}]", app.sorting);
+    let view = TableView {
+        title: &title,
+        columns: &RAW_COLUMNS,
+        selected: app.selected_raw(),
+        highlight_style,
+    };
+    draw_table(f, area, view, &rows, |row| raw_row_cells(row));
+}
+
+fn unix_row_cells(row: &UnixSocketRow, peer: Option<&UnixSocketRow>, wide: bool) -> Vec<String> {
+    let mut cells = vec![
+        row.socket_type.to_string(),
+        row.state.to_string(),
+        row.path.clone().unwrap_or_default(),
+    ];
+    if wide {
+        cells.push(row.inode.to_string());
+    }
+    cells.push(display_or_star(&row.peer_inode));
+    if wide {
+        cells.push(
+            peer.and_then(|peer| peer.process_name.clone())
+                .unwrap_or_default(),
+        );
+    }
+    cells.push(join_pids(&row.pids));
+    cells.push(row.process_name.clone().unwrap_or_default());
+    cells
+}
+
+fn raw_row_cells(row: &RawSocketRow) -> Vec<String> {
+    vec![
+        row.kind.to_string(),
+        row.protocol_name(),
+        row.endpoint.clone(),
+        display_or_star(&row.uid),
+        row.inode.to_string(),
+        join_pids(&row.pids),
+        row.process_name.clone().unwrap_or_default(),
+    ]
+}
+
+fn socket_row_cells(row: &SocketRow, extras: Extras, now: SystemTime) -> Vec<String> {
+    let mut cells = match row.protocol {
+        Protocol::Tcp => vec![
+            row.family.to_string(),
+            row.local_addr.to_string(),
+            row.local_port.to_string(),
+            display_or_star(&row.remote_addr),
+            display_or_star(&row.remote_port),
+            display_or_star(&row.state),
+        ],
+        Protocol::Udp => vec![
+            row.family.to_string(),
+            row.local_addr.to_string(),
+            row.local_port.to_string(),
+        ],
+    };
+
+    if extras.internals {
+        cells.push(display_or_star(&row.queues.map(|queues| queues.recv)));
+        cells.push(display_or_star(&row.queues.map(|queues| queues.send)));
+        if row.protocol == Protocol::Tcp {
+            let tcp_info = row.tcp_info;
+            cells.push(display_or_star(
+                &tcp_info.map(|info| format!("{:.1}", f64::from(info.rtt_us) / 1000.0)),
+            ));
+            cells.push(display_or_star(&tcp_info.map(|info| info.snd_cwnd)));
+            cells.push(display_or_star(&tcp_info.map(|info| info.retransmits)));
+        }
+    }
+    if extras.throughput && row.protocol == Protocol::Tcp {
+        let throughput = row.throughput;
+        cells.push(display_or_star(&throughput.map(|t| format_bytes(t.recv))));
+        cells.push(display_or_star(&throughput.map(|t| format_bytes(t.send))));
+    }
+
+    if extras.age {
+        let age = now.duration_since(row.first_seen).unwrap_or_default();
+        cells.push(format_duration(age));
     }
+    cells.push(join_pids(&row.pids));
+    cells.push(row.process_name.clone().unwrap_or_default());
+    cells
 }
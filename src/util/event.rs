@@ -1,8 +1,7 @@
-
 use std::io;
-use std::sync::mpsc;
+use std::sync::mpsc::{self, RecvTimeoutError};
 use std::thread;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
 use termion::event::Key;
 use termion::input::TermRead;
@@ -14,70 +13,79 @@ pub enum Event<I> {
 
 /// A small event handler that wrap termion input and tick events. Each event
 /// type is handled in its own thread and returned to a common `Receiver`
+///
+/// The tick thread takes a new tick rate at any time, and stops once `Events`
+/// is dropped.
 pub struct Events {
     rx: mpsc::Receiver<Event<Key>>,
+    tick_rate_tx: mpsc::Sender<Duration>,
+    #[allow(dead_code)]
     input_handle: thread::JoinHandle<()>,
+    #[allow(dead_code)]
     tick_handle: thread::JoinHandle<()>,
 }
 
 #[derive(Debug, Clone, Copy)]
 pub struct Config {
-    pub exit_key: Key,
     pub tick_rate: Duration,
 }
 
 impl Default for Config {
     fn default() -> Config {
         Config {
-            exit_key: Key::Char('q'),
             tick_rate: Duration::from_millis(250),
         }
     }
 }
 
 impl Events {
-    pub fn new() -> Events {
-        Events::with_config(Config::default())
-    }
-
     pub fn with_config(config: Config) -> Events {
         let (tx, rx) = mpsc::channel();
         let input_handle = {
             let tx = tx.clone();
             thread::spawn(move || {
                 let stdin = io::stdin();
-                for evt in stdin.keys() {
-                    match evt {
-                        Ok(key) => {
-                            if let Err(_) = tx.send(Event::Input(key)) {
-                                return;
-                            }
-                            if key == config.exit_key {
-                                return;
-                            }
-                        }
-                        Err(_) => {}
+                for key in stdin.keys().flatten() {
+                    if tx.send(Event::Input(key)).is_err() {
+                        return;
                     }
                 }
             })
         };
+        let (tick_rate_tx, tick_rate_rx) = mpsc::channel();
         let tick_handle = {
             let tx = tx.clone();
             thread::spawn(move || {
-                let tx = tx.clone();
+                let mut tick_rate = config.tick_rate;
+                let mut last_tick = Instant::now();
                 loop {
-                    tx.send(Event::Tick).unwrap();
-                    thread::sleep(config.tick_rate);
+                    let wait = tick_rate.checked_sub(last_tick.elapsed());
+                    match wait.map(|wait| tick_rate_rx.recv_timeout(wait)) {
+                        Some(Ok(new_rate)) => tick_rate = new_rate,
+                        Some(Err(RecvTimeoutError::Disconnected)) => return,
+                        None | Some(Err(RecvTimeoutError::Timeout)) => {
+                            if tx.send(Event::Tick).is_err() {
+                                return;
+                            }
+                            last_tick = Instant::now();
+                        }
+                    }
                 }
             })
         };
         Events {
             rx,
+            tick_rate_tx,
             input_handle,
             tick_handle,
         }
     }
 
+    /// Takes effect right away: a tick overdue at the new rate is sent now.
+    pub fn set_tick_rate(&self, tick_rate: Duration) {
+        let _ = self.tick_rate_tx.send(tick_rate);
+    }
+
     pub fn next(&self) -> Result<Event<Key>, mpsc::RecvError> {
         self.rx.recv()
     }
@@ -1,30 +1,36 @@
 pub mod event;
 
-#[derive(Clone)]
-pub struct SinSignal {
-    x: f64,
-    interval: f64,
-    period: f64,
-    scale: f64,
+use std::collections::VecDeque;
+
+/// Keeps the last `capacity` values pushed, dropping the oldest ones.
+#[derive(Debug, Clone)]
+pub struct RingBuffer<T> {
+    values: VecDeque<T>,
+    capacity: usize,
 }
 
-impl SinSignal {
-    pub fn new(interval: f64, period: f64, scale: f64) -> SinSignal {
-        SinSignal {
-            x: 0.0,
-            interval,
-            period,
-            scale,
+impl<T> RingBuffer<T> {
+    pub fn new(capacity: usize) -> RingBuffer<T> {
+        RingBuffer {
+            values: VecDeque::with_capacity(capacity),
+            capacity,
         }
     }
-}
 
-impl Iterator for SinSignal {
-    type Item = (f64, f64);
-    fn next(&mut self) -> Option<Self::Item> {
-        let point = (self.x, (self.x * 1.0 / self.period).sin() * self.scale);
-        self.x += self.interval;
-        Some(point)
+    pub fn push(&mut self, value: T) {
+        if self.values.len() == self.capacity {
+            self.values.pop_front();
+        }
+        self.values.push_back(value);
+    }
+
+    pub fn last(&self) -> Option<&T> {
+        self.values.back()
+    }
+
+    /// Oldest value first.
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = &T> + ExactSizeIterator {
+        self.values.iter()
     }
 }
 
@@ -34,7 +40,7 @@ pub struct TabsState<'a> {
 }
 
 impl<'a> TabsState<'a> {
-    pub fn new(titles: Vec<&'a str>) -> TabsState {
+    pub fn new(titles: Vec<&'a str>) -> TabsState<'a> {
         TabsState { titles, index: 0 }
     }
     pub fn next(&mut self) {
@@ -49,3 +55,19 @@ impl<'a> TabsState<'a> {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::RingBuffer;
+
+    #[test]
+    fn oldest_values_are_dropped() {
+        let mut buffer = RingBuffer::new(3);
+        for value in 1..=5 {
+            buffer.push(value);
+        }
+
+        assert_eq!(buffer.iter().copied().collect::<Vec<_>>(), vec![3, 4, 5]);
+        assert_eq!(buffer.last(), Some(&5));
+    }
+}